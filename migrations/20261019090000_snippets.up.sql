@@ -0,0 +1,7 @@
+CREATE TABLE snippets (
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    language TEXT NOT NULL,
+    code TEXT NOT NULL,
+    PRIMARY KEY (user_id, name)
+)
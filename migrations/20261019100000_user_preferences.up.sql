@@ -0,0 +1,8 @@
+CREATE TABLE user_preferences (
+    user_id INTEGER NOT NULL PRIMARY KEY,
+    location_name TEXT,
+    lat TEXT,
+    lon TEXT,
+    units TEXT NOT NULL DEFAULT 'both',
+    share_location BOOLEAN NOT NULL DEFAULT false
+)
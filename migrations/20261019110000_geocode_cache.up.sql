@@ -0,0 +1,7 @@
+CREATE TABLE geocode_cache (
+    query TEXT NOT NULL PRIMARY KEY,
+    display_name TEXT NOT NULL,
+    lat TEXT NOT NULL,
+    lon TEXT NOT NULL,
+    last_updated INTEGER NOT NULL
+)
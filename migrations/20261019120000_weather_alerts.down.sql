@@ -0,0 +1,2 @@
+DROP TABLE posted_weather_alerts;
+DROP TABLE weather_alert_subscriptions;
\ No newline at end of file
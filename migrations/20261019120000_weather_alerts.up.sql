@@ -0,0 +1,17 @@
+CREATE TABLE weather_alert_subscriptions (
+    id INTEGER NOT NULL PRIMARY KEY,
+    guild INTEGER NOT NULL,
+    channel INTEGER NOT NULL,
+    location_name TEXT NOT NULL,
+    lat TEXT NOT NULL,
+    lon TEXT NOT NULL,
+    UNIQUE (channel, lat, lon)
+);
+
+CREATE TABLE posted_weather_alerts (
+    subscription_id INTEGER NOT NULL,
+    alert_id TEXT NOT NULL,
+    posted_at INTEGER NOT NULL,
+    PRIMARY KEY (subscription_id, alert_id),
+    FOREIGN KEY (subscription_id) REFERENCES weather_alert_subscriptions(id) ON DELETE CASCADE
+);
@@ -0,0 +1 @@
+DROP TABLE animal_images;
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE animal_images (
+    id INTEGER NOT NULL PRIMARY KEY,
+    pool TEXT NOT NULL,
+    url TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL,
+    served BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (pool, url)
+)
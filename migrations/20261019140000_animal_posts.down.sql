@@ -0,0 +1,2 @@
+DROP TABLE animal_reactions;
+DROP TABLE animal_posts;
\ No newline at end of file
@@ -0,0 +1,16 @@
+CREATE TABLE animal_posts (
+    message_id INTEGER NOT NULL PRIMARY KEY,
+    guild INTEGER,
+    channel INTEGER NOT NULL,
+    species TEXT NOT NULL,
+    url TEXT NOT NULL,
+    posted_at INTEGER NOT NULL
+);
+
+CREATE TABLE animal_reactions (
+    message_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    emoji TEXT NOT NULL,
+    PRIMARY KEY (message_id, user_id, emoji),
+    FOREIGN KEY (message_id) REFERENCES animal_posts(message_id) ON DELETE CASCADE
+);
@@ -0,0 +1 @@
+DROP TABLE guild_rocks;
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE guild_rocks (
+    guild INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (guild, user_id)
+);
+
+CREATE INDEX guild_rocks_count ON guild_rocks (guild, count);
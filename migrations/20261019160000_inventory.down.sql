@@ -0,0 +1 @@
+DROP TABLE inventory;
\ No newline at end of file
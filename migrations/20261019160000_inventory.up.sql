@@ -0,0 +1,6 @@
+CREATE TABLE inventory (
+    user_id INTEGER NOT NULL,
+    item TEXT NOT NULL,
+    quantity INTEGER NOT NULL,
+    PRIMARY KEY (user_id, item)
+);
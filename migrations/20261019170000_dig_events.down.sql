@@ -0,0 +1 @@
+DROP TABLE dig_events;
\ No newline at end of file
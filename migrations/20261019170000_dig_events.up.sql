@@ -0,0 +1,10 @@
+CREATE TABLE dig_events (
+    id INTEGER NOT NULL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    guild INTEGER,
+    channel INTEGER NOT NULL,
+    dug_at INTEGER NOT NULL
+);
+
+CREATE INDEX dig_events_user ON dig_events (user_id, dug_at);
+CREATE INDEX dig_events_guild ON dig_events (guild, dug_at);
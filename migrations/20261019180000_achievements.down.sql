@@ -0,0 +1,3 @@
+DROP TABLE achievement_roles;
+DROP TABLE earned_achievements;
+DROP TABLE achievements;
\ No newline at end of file
@@ -0,0 +1,29 @@
+CREATE TABLE achievements (
+    id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    threshold INTEGER NOT NULL
+);
+
+INSERT INTO achievements (id, name, description, kind, threshold) VALUES
+    ('digs_100', 'Apprentice digger', 'Dig 100 times', 'digs', 100),
+    ('digs_1000', 'Excavator', 'Dig 1,000 times', 'digs', 1000),
+    ('digs_10000', 'Mole person', 'Dig 10,000 times', 'digs', 10000),
+    ('streak_7', 'Dedicated digger', 'Dig every day for a week', 'daily_streak', 7);
+
+CREATE TABLE earned_achievements (
+    user_id INTEGER NOT NULL,
+    achievement_id TEXT NOT NULL,
+    earned_at INTEGER NOT NULL,
+    PRIMARY KEY (user_id, achievement_id),
+    FOREIGN KEY (achievement_id) REFERENCES achievements(id) ON DELETE CASCADE
+);
+
+CREATE TABLE achievement_roles (
+    guild INTEGER NOT NULL,
+    achievement_id TEXT NOT NULL,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (guild, achievement_id),
+    FOREIGN KEY (achievement_id) REFERENCES achievements(id) ON DELETE CASCADE
+);
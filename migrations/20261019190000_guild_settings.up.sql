@@ -0,0 +1,8 @@
+CREATE TABLE guild_settings (
+    guild INTEGER NOT NULL PRIMARY KEY,
+    prefix TEXT,
+    disabled_commands TEXT NOT NULL DEFAULT '',
+    disabled_categories TEXT NOT NULL DEFAULT '',
+    countdown_channel INTEGER,
+    locale TEXT
+);
@@ -0,0 +1 @@
+ALTER TABLE countdowns DROP COLUMN channel;
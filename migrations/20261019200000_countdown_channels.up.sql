@@ -0,0 +1 @@
+ALTER TABLE countdowns ADD COLUMN channel INTEGER;
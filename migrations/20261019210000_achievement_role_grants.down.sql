@@ -0,0 +1 @@
+DROP TABLE achievement_role_grants;
@@ -0,0 +1,6 @@
+CREATE TABLE achievement_role_grants (
+    guild INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (guild, user_id, role_id)
+);
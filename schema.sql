@@ -30,7 +30,8 @@ create table countdowns
         primary key,
     end INTEGER not null,
     active BOOLEAN not null,
-    guild INTEGER default 307765179373060096
+    guild INTEGER default 307765179373060096,
+    channel INTEGER
 );
 
 create table rocks
@@ -39,3 +40,171 @@ create table rocks
         primary key,
     count INTEGER default 1 not null
 );
+
+create table snippets
+(
+    user_id INTEGER not null,
+    name TEXT not null,
+    language TEXT not null,
+    code TEXT not null,
+    primary key (user_id, name)
+);
+
+create table user_preferences
+(
+    user_id INTEGER not null
+        primary key,
+    location_name TEXT,
+    lat TEXT,
+    lon TEXT,
+    units TEXT default 'both' not null,
+    share_location BOOLEAN default false not null
+);
+
+create table geocode_cache
+(
+    query TEXT not null
+        primary key,
+    display_name TEXT not null,
+    lat TEXT not null,
+    lon TEXT not null,
+    last_updated INTEGER not null
+);
+
+create table weather_alert_subscriptions
+(
+    id INTEGER not null
+        primary key,
+    guild INTEGER not null,
+    channel INTEGER not null,
+    location_name TEXT not null,
+    lat TEXT not null,
+    lon TEXT not null,
+    unique (channel, lat, lon)
+);
+
+create table posted_weather_alerts
+(
+    subscription_id INTEGER not null
+        references weather_alert_subscriptions
+            on delete cascade,
+    alert_id TEXT not null,
+    posted_at INTEGER not null,
+    primary key (subscription_id, alert_id)
+);
+
+create table animal_images
+(
+    id INTEGER not null
+        primary key,
+    pool TEXT not null,
+    url TEXT not null,
+    fetched_at INTEGER not null,
+    served BOOLEAN default FALSE not null,
+    unique (pool, url)
+);
+
+create table animal_posts
+(
+    message_id INTEGER not null
+        primary key,
+    guild INTEGER,
+    channel INTEGER not null,
+    species TEXT not null,
+    url TEXT not null,
+    posted_at INTEGER not null
+);
+
+create table animal_reactions
+(
+    message_id INTEGER not null
+        references animal_posts
+            on delete cascade,
+    user_id INTEGER not null,
+    emoji TEXT not null,
+    primary key (message_id, user_id, emoji)
+);
+
+create table guild_rocks
+(
+    guild INTEGER not null,
+    user_id INTEGER not null,
+    count INTEGER not null,
+    primary key (guild, user_id)
+);
+
+create index guild_rocks_count
+    on guild_rocks (guild, count);
+
+create table inventory
+(
+    user_id INTEGER not null,
+    item TEXT not null,
+    quantity INTEGER not null,
+    primary key (user_id, item)
+);
+
+create table dig_events
+(
+    id INTEGER not null
+        primary key,
+    user_id INTEGER not null,
+    guild INTEGER,
+    channel INTEGER not null,
+    dug_at INTEGER not null
+);
+
+create index dig_events_user
+    on dig_events (user_id, dug_at);
+
+create index dig_events_guild
+    on dig_events (guild, dug_at);
+
+create table achievements
+(
+    id TEXT not null
+        primary key,
+    name TEXT not null,
+    description TEXT not null,
+    kind TEXT not null,
+    threshold INTEGER not null
+);
+
+create table earned_achievements
+(
+    user_id INTEGER not null,
+    achievement_id TEXT not null
+        references achievements
+            on delete cascade,
+    earned_at INTEGER not null,
+    primary key (user_id, achievement_id)
+);
+
+create table achievement_roles
+(
+    guild INTEGER not null,
+    achievement_id TEXT not null
+        references achievements
+            on delete cascade,
+    role_id INTEGER not null,
+    primary key (guild, achievement_id)
+);
+
+create table achievement_role_grants
+(
+    guild INTEGER not null,
+    user_id INTEGER not null,
+    role_id INTEGER not null,
+    primary key (guild, user_id, role_id)
+);
+
+create table guild_settings
+(
+    guild INTEGER not null
+        primary key,
+    prefix TEXT,
+    disabled_commands TEXT default '' not null,
+    disabled_categories TEXT default '' not null,
+    countdown_channel INTEGER,
+    locale TEXT
+);
@@ -1,33 +1,340 @@
-use crate::AnimalGatewayContainer;
+use crate::commands::invalid_command;
+use crate::models::zoo::{Breed, Species, FAVOURITE_EMOJI, UPVOTE_EMOJI};
+use crate::{AnimalGatewayContainer, AnimalPostStoreContainer};
+use anyhow::Result;
 use serenity::client::Context;
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandResult};
-use serenity::model::channel::Message;
+use serenity::model::channel::{Message, Reaction};
+use tracing::warn;
+
+/// How many pictures `~dog favourites` and `~zoo top` list.
+const LIST_LIMIT: i64 = 10;
+
+/// Replies with an image of the given species, or explains which source is unavailable.
+async fn reply_with_species(
+    ctx: &Context,
+    msg: &Message,
+    title: &str,
+    species: &Species,
+) -> CommandResult {
+    let image = {
+        let data = ctx.data.read().await;
+        let animal_gateway = data
+            .get::<AnimalGatewayContainer>()
+            .expect("failed to obtain animal gateway");
+        animal_gateway.get_image(species).await
+    };
+
+    match image {
+        Ok(url) => reply_with_image(ctx, msg, title, species, &url).await,
+        Err(why) => {
+            warn!("failed to get animal image: {why:?}");
+            let _ = msg
+                .reply(
+                    ctx,
+                    format!(
+                        "{} isn't responding right now, try again later.",
+                        species.source()
+                    ),
+                )
+                .await;
+            Ok(())
+        }
+    }
+}
+
+/// Replies to the given message with an embedded image, remembering the reply so that
+/// reactions to it can be tracked.
+async fn reply_with_image(
+    ctx: &Context,
+    msg: &Message,
+    title: &str,
+    species: &Species,
+    url: &str,
+) -> CommandResult {
+    let reply = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.reference_message(msg);
+            m.embed(|e| {
+                e.title(title);
+                e.url(url);
+                e.image(url);
+                e.footer(|ef| {
+                    ef.text(format!(
+                        "React {FAVOURITE_EMOJI} to save, {UPVOTE_EMOJI} to upvote"
+                    ));
+                    ef
+                });
+                e
+            });
+
+            m
+        })
+        .await?;
+
+    let data = ctx.data.read().await;
+    let post_store = data
+        .get::<AnimalPostStoreContainer>()
+        .expect("failed to obtain animal post store");
+    post_store
+        .record_post(
+            reply.id.0 as i64,
+            msg.guild_id.map(|guild_id| guild_id.0 as i64),
+            msg.channel_id.0 as i64,
+            &species.pool_key(),
+            url,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Tracks favourites and upvotes on our animal pictures.
+pub(crate) async fn record_reaction(ctx: &Context, reaction: &Reaction, added: bool) -> Result<()> {
+    let Some(user_id) = reaction.user_id else {
+        return Ok(());
+    };
+    if user_id == ctx.cache.current_user_id() {
+        return Ok(());
+    }
+
+    let Some(emoji) = [FAVOURITE_EMOJI, UPVOTE_EMOJI]
+        .into_iter()
+        .find(|emoji| reaction.emoji.unicode_eq(emoji))
+    else {
+        return Ok(());
+    };
 
-#[command]
-async fn dog(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let post_store = data
+        .get::<AnimalPostStoreContainer>()
+        .expect("failed to obtain animal post store");
+
+    let message_id = reaction.message_id.0 as i64;
+    if added {
+        post_store
+            .add_reaction(message_id, user_id.0 as i64, emoji)
+            .await
+    } else {
+        post_store
+            .remove_reaction(message_id, user_id.0 as i64, emoji)
+            .await
+    }
+}
+
+/// Lists the pictures of the given species the author has favourited.
+async fn favourites(ctx: &Context, msg: &Message, species: &Species) -> CommandResult {
+    let data = ctx.data.read().await;
+    let post_store = data
+        .get::<AnimalPostStoreContainer>()
+        .expect("failed to obtain animal post store");
+
+    let favourites = post_store
+        .favourites(msg.author.id.0 as i64, &species.pool_key(), LIST_LIMIT)
+        .await?;
+
+    let Some(latest) = favourites.first() else {
+        let _ = msg
+            .reply(
+                ctx,
+                format!(
+                    "You haven't saved any yet, react {FAVOURITE_EMOJI} to a picture to save it."
+                ),
+            )
+            .await;
+        return Ok(());
+    };
+
+    let description = favourites
+        .iter()
+        .enumerate()
+        .map(|(i, post)| format!("{}. [{}]({})", i + 1, post.species, post.url))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.reference_message(msg);
+            m.embed(|e| {
+                e.title(format!("{}'s favourites", msg.author.name));
+                e.description(description);
+                e.image(&latest.url);
+                e
+            });
+
+            m
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn dog_breeds(ctx: &Context, msg: &Message) -> CommandResult {
     let data = ctx.data.read().await;
     let animal_gateway = data
         .get::<AnimalGatewayContainer>()
         .expect("failed to obtain animal gateway");
 
-    let url = match args.current() {
-        Some("golden") => animal_gateway.get_golden().await?,
-        _ => animal_gateway.get_dog().await?,
-    };
+    let breeds = animal_gateway.get_dog_breeds().await?;
+    let description = breeds
+        .iter()
+        .map(|(breed, sub_breeds)| match sub_breeds.as_slice() {
+            [] => breed.clone(),
+            sub_breeds => format!("{breed} ({})", sub_breeds.join(", ")),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.reference_message(msg);
+            m.embed(|e| {
+                e.title("Dog breeds");
+                e.description(description.chars().take(4000).collect::<String>());
+                e
+            });
+
+            m
+        })
+        .await?;
 
-    msg.reply(ctx, url).await?;
     Ok(())
 }
 
 #[command]
-async fn cat(ctx: &Context, msg: &Message) -> CommandResult {
+async fn dog(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let words: Vec<&str> = args.rest().split_whitespace().collect();
+    match words.as_slice() {
+        ["breeds"] => return dog_breeds(ctx, msg).await,
+        ["favourites" | "favorites"] => return favourites(ctx, msg, &Species::Dog(None)).await,
+        _ => {}
+    }
+
+    if words.is_empty() {
+        return reply_with_species(ctx, msg, "Woof!", &Species::Dog(None)).await;
+    }
+
+    let breeds = {
+        let data = ctx.data.read().await;
+        let animal_gateway = data
+            .get::<AnimalGatewayContainer>()
+            .expect("failed to obtain animal gateway");
+        animal_gateway.get_dog_breeds().await?
+    };
+    let Some(breed) = Breed::resolve(&breeds, &words) else {
+        let _ = msg
+            .reply(
+                ctx,
+                "I don't know that breed, see `~dog breeds` for the ones I do.",
+            )
+            .await;
+        return Ok(());
+    };
+
+    reply_with_species(ctx, msg, "Woof!", &Species::Dog(Some(breed))).await
+}
+
+/// Splits `[tag] [says <text>]` into the tag and the text.
+fn parse_cat_args(input: &str) -> (Option<&str>, Option<&str>) {
+    let input = input.trim();
+    let (tag, rest) = match input.split_once(char::is_whitespace) {
+        Some((first, rest)) if first != "says" => (Some(first), rest.trim_start()),
+        None if !input.is_empty() && input != "says" => (Some(input), ""),
+        _ => (None, input),
+    };
+
+    let says = match rest.split_once(char::is_whitespace) {
+        Some(("says", text)) => Some(text.trim()),
+        _ => None,
+    };
+    (tag, says)
+}
+
+#[command]
+async fn cat(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if matches!(args.rest().trim(), "favourites" | "favorites") {
+        return favourites(ctx, msg, &Species::Cat).await;
+    }
+
+    let (tag, says) = parse_cat_args(args.rest());
+    if tag.is_none() && says.is_none() {
+        return reply_with_species(ctx, msg, "Meow!", &Species::Cat).await;
+    }
+
     let data = ctx.data.read().await;
     let animal_gateway = data
         .get::<AnimalGatewayContainer>()
         .expect("failed to obtain animal gateway");
 
-    let url = animal_gateway.get_cat().await?;
-    msg.reply(ctx, url).await?;
+    match animal_gateway.get_cat(tag, says).await? {
+        Some(url) => reply_with_image(ctx, msg, "Meow!", &Species::Cat, &url).await,
+        None => {
+            let _ = msg.reply(ctx, "I couldn't find any cats like that.").await;
+            Ok(())
+        }
+    }
+}
+
+#[command]
+async fn fox(ctx: &Context, msg: &Message) -> CommandResult {
+    reply_with_species(ctx, msg, "Floof!", &Species::Fox).await
+}
+
+#[command]
+async fn duck(ctx: &Context, msg: &Message) -> CommandResult {
+    reply_with_species(ctx, msg, "Quack!", &Species::Duck).await
+}
+
+/// Usage: `~zoo top`
+#[command]
+async fn zoo(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let Some(guild_id) = msg.guild_id else {
+        return invalid_command(ctx, msg).await;
+    };
+    if args.current() != Some("top") {
+        return invalid_command(ctx, msg).await;
+    }
+
+    let data = ctx.data.read().await;
+    let post_store = data
+        .get::<AnimalPostStoreContainer>()
+        .expect("failed to obtain animal post store");
+
+    let top = post_store.top(guild_id.0 as i64, LIST_LIMIT).await?;
+    let Some(best) = top.first() else {
+        let _ = msg
+            .reply(
+                ctx,
+                format!(
+                    "Nothing has been upvoted yet, react {UPVOTE_EMOJI} to a picture to upvote it."
+                ),
+            )
+            .await;
+        return Ok(());
+    };
+
+    let description = top
+        .iter()
+        .enumerate()
+        .map(|(i, image)| format!("{}. [{} {UPVOTE_EMOJI}]({})", i + 1, image.votes, image.url))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.reference_message(msg);
+            m.embed(|e| {
+                e.title("Top animal pictures");
+                e.description(description);
+                e.image(&best.url);
+                e
+            });
+
+            m
+        })
+        .await?;
+
     Ok(())
 }
@@ -0,0 +1,53 @@
+use crate::commands::invalid_command;
+use crate::models::calc::Evaluation;
+use crate::CalculatorContainer;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::channel::Message;
+use serenity::prelude::*;
+
+/// Usage: `~calc <expression> [in <unit>]`, `~calc vars` or `~calc clear`
+///
+/// Variables are assigned with `~calc x = 2 * pi`, and `ans` holds the last result.
+#[command]
+async fn calc(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let input = args.rest().trim().trim_matches('`');
+    if input.is_empty() {
+        return invalid_command(ctx, msg).await;
+    }
+
+    let data = ctx.data.read().await;
+    let calculator = data
+        .get::<CalculatorContainer>()
+        .expect("failed to obtain calculator");
+    let user_id = msg.author.id.0;
+
+    let response = match input {
+        "vars" => {
+            let variables = calculator.variables(user_id);
+            if variables.is_empty() {
+                "You don't have any variables.".to_string()
+            } else {
+                variables
+                    .iter()
+                    .map(|(name, value)| format!("`{name} = {value}`"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        "clear" => {
+            calculator.clear(user_id);
+            "Cleared your variables.".to_string()
+        }
+        input => match calculator.evaluate(user_id, input) {
+            Ok(Evaluation {
+                name: Some(name),
+                value,
+            }) => format!("`{name} = {value}`"),
+            Ok(Evaluation { name: None, value }) => format!("`{value}`"),
+            Err(why) => format!("{why}"),
+        },
+    };
+
+    let _ = msg.reply(ctx, response).await;
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use crate::commands::{invalid_command, is_guild_admin};
+use crate::models::guild_settings::{Setting, DEFAULT_PREFIX};
+use crate::{GuildSettingsContainer, GENERAL_GROUP, MTG_GROUP};
+use anyhow::{bail, Result};
+use serenity::framework::standard::macros::{command, hook};
+use serenity::framework::standard::{Args, CommandGroup, CommandResult};
+use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
+use serenity::prelude::*;
+use tracing::warn;
+
+/// Commands which can't be disabled, even by disabling their category, so that a guild
+/// can't lock itself out.
+const ALWAYS_ENABLED: &[&str] = &["config", "help"];
+
+fn groups() -> [&'static CommandGroup; 2] {
+    [&GENERAL_GROUP, &MTG_GROUP]
+}
+
+/// Returns the name of the group the given command belongs to.
+fn category(command: &str) -> Option<&'static str> {
+    groups()
+        .into_iter()
+        .find(|group| {
+            group
+                .options
+                .commands
+                .iter()
+                .any(|c| c.options.names.contains(&command))
+        })
+        .map(|group| group.name)
+}
+
+/// Returns the guild's configured prefix, or the default outside of guilds.
+pub(crate) async fn prefix_for(ctx: &Context, guild_id: Option<GuildId>) -> String {
+    let Some(guild_id) = guild_id else {
+        return DEFAULT_PREFIX.to_string();
+    };
+
+    let data = ctx.data.read().await;
+    let guild_settings = data
+        .get::<GuildSettingsContainer>()
+        .expect("failed to obtain guild settings");
+
+    match guild_settings.get(guild_id.0 as i64).await {
+        Ok(config) => config.prefix().to_string(),
+        Err(why) => {
+            warn!("failed to get prefix for guild {guild_id}: {why:?}");
+            DEFAULT_PREFIX.to_string()
+        }
+    }
+}
+
+/// Uses the guild's configured prefix, or the default outside of guilds.
+#[hook]
+pub(crate) async fn dynamic_prefix(ctx: &Context, msg: &Message) -> Option<String> {
+    Some(prefix_for(ctx, msg.guild_id).await)
+}
+
+/// Stops commands which the guild has disabled from running.
+#[hook]
+pub(crate) async fn check_enabled(ctx: &Context, msg: &Message, command_name: &str) -> bool {
+    let Some(guild_id) = msg.guild_id else {
+        return true;
+    };
+    if ALWAYS_ENABLED.contains(&command_name) {
+        return true;
+    }
+
+    let config = {
+        let data = ctx.data.read().await;
+        let guild_settings = data
+            .get::<GuildSettingsContainer>()
+            .expect("failed to obtain guild settings");
+        guild_settings.get(guild_id.0 as i64).await
+    };
+
+    match config {
+        Ok(config) if config.is_disabled(command_name, category(command_name)) => {
+            let _ = msg.react(ctx, '🚫').await;
+            false
+        }
+        Ok(_) => true,
+        Err(why) => {
+            warn!("failed to get settings for guild {guild_id}: {why:?}");
+            true
+        }
+    }
+}
+
+/// Checks that every command or category named in a list exists, and can be disabled.
+fn check_names(setting: Setting, value: &str) -> Result<()> {
+    for name in value.split([' ', ',']).filter(|name| !name.is_empty()) {
+        let exists = match setting {
+            Setting::DisabledCommands => {
+                if ALWAYS_ENABLED.contains(&name.to_lowercase().as_str()) {
+                    bail!("{name} can't be disabled");
+                }
+                category(&name.to_lowercase()).is_some()
+            }
+            Setting::DisabledCategories => groups()
+                .iter()
+                .any(|group| group.name.eq_ignore_ascii_case(name)),
+            _ => true,
+        };
+        if !exists {
+            bail!("there's nothing called {name} to disable");
+        }
+    }
+
+    Ok(())
+}
+
+/// Usage: `~config get [setting]`, `~config set <setting> <value>` or
+/// `~config reset [setting]`
+///
+/// The settings are `prefix`, `disabled_commands`, `disabled_categories`,
+/// `countdown_channel` and `locale`.  Requires the Manage Server permission.
+#[command]
+async fn config(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let Some(guild_id) = msg.guild_id else {
+        return invalid_command(ctx, msg).await;
+    };
+    if !is_guild_admin(ctx, msg).await {
+        let _ = msg
+            .reply(ctx, "You need the Manage Server permission to do that.")
+            .await;
+        return Ok(());
+    }
+    let guild = guild_id.0 as i64;
+
+    let mode = args.single::<String>().unwrap_or_default();
+    let setting = match args.single::<String>() {
+        Ok(setting) => match setting.parse::<Setting>() {
+            Ok(setting) => Some(setting),
+            Err(why) => {
+                let _ = msg.reply(ctx, format!("{why}")).await;
+                return Ok(());
+            }
+        },
+        Err(_) => None,
+    };
+    let value = args.rest();
+
+    let data = ctx.data.read().await;
+    let guild_settings = data
+        .get::<GuildSettingsContainer>()
+        .expect("failed to obtain guild settings");
+    let mut config = guild_settings.get(guild).await?;
+
+    let response = match (mode.as_str(), setting) {
+        ("get", Some(setting)) => format!("{}: {}", setting.as_str(), config.describe(setting)),
+        ("get", None) => Setting::ALL
+            .iter()
+            .map(|setting| format!("{}: {}", setting.as_str(), config.describe(*setting)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ("set", Some(setting)) => {
+            if let Err(why) = check_names(setting, value).and_then(|_| config.set(setting, value)) {
+                let _ = msg.reply(ctx, format!("{why}")).await;
+                return Ok(());
+            }
+            guild_settings.save(guild, &config).await?;
+            format!("{} is now {}", setting.as_str(), config.describe(setting))
+        }
+        ("reset", Some(setting)) => {
+            config.reset(setting);
+            guild_settings.save(guild, &config).await?;
+            format!(
+                "{} is back to {}",
+                setting.as_str(),
+                config.describe(setting)
+            )
+        }
+        ("reset", None) => {
+            guild_settings.reset(guild).await?;
+            "All settings are back to their defaults.".to_string()
+        }
+        _ => return invalid_command(ctx, msg).await,
+    };
+
+    let _ = msg.reply(ctx, response).await;
+    Ok(())
+}
@@ -1,13 +1,23 @@
 use crate::commands::invalid_command;
 use crate::containers::AppInfoContainer;
+use crate::models::countdowns::CountdownStore;
+use crate::models::guild_settings::GuildSettings;
 use crate::CountdownStoreContainer;
 use anyhow::anyhow;
 use chrono::{DateTime, Utc};
 use serenity::client::Context;
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandResult};
+use serenity::http::Http;
 use serenity::model::channel::Message;
-use tracing::info;
+use serenity::model::id::ChannelId;
+use serenity::utils::parse_channel;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long announcing a countdown is retried for, in seconds, in case its channel is gone.
+const ANNOUNCE_RETRY_SECS: i64 = 24 * 60 * 60;
 
 async fn ensure_in_guild(ctx: &Context, msg: &Message) -> Option<i64> {
     // FIXME: this is an unnecessary restriction, just a little annoying to work around it.
@@ -82,6 +92,10 @@ async fn list_countdowns(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
+/// Usage: `~countdown add <date> [#channel]`
+///
+/// The countdown is announced in the given channel when it ends, or otherwise in the
+/// guild's `countdown_channel`.
 async fn add_countdown(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let data = ctx.data.read().await;
 
@@ -99,12 +113,20 @@ async fn add_countdown(ctx: &Context, msg: &Message, mut args: Args) -> CommandR
 
     match args.single_quoted::<DateTime<Utc>>() {
         Ok(dt) => {
+            let channel = match args.rest().trim() {
+                "" => None,
+                channel => match parse_channel(channel) {
+                    Some(channel) => Some(channel as i64),
+                    None => return invalid_command(ctx, msg).await,
+                },
+            };
+
             let countdown_store = data
                 .get::<CountdownStoreContainer>()
                 .expect("failed to obtain countdown store");
 
             countdown_store
-                .insert(dt.timestamp(), guild_id)
+                .insert(dt.timestamp(), guild_id, channel)
                 .await
                 .expect("failed to insert countdown");
 
@@ -140,3 +162,57 @@ async fn countdown(ctx: &Context, msg: &Message, mut args: Args) -> CommandResul
         next_countdown(ctx, msg).await
     }
 }
+
+/// Periodically announces countdowns which have ended, in their own channel or the guild's
+/// default countdown channel.  Countdowns with neither are finished without an announcement.
+pub(crate) async fn announce_countdowns(
+    http: Arc<Http>,
+    countdown_store: CountdownStore<'static>,
+    guild_settings: Arc<GuildSettings<'static>>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        let now = Utc::now().timestamp();
+        let ended = match countdown_store.ended(now).await {
+            Ok(ended) => ended,
+            Err(why) => {
+                warn!("failed to get ended countdowns: {why:?}");
+                continue;
+            }
+        };
+
+        for countdown in ended {
+            let channel = match countdown.channel {
+                Some(channel) => Some(channel),
+                None => match guild_settings.get(countdown.guild).await {
+                    Ok(config) => config.countdown_channel,
+                    Err(why) => {
+                        warn!("failed to get countdown channel: {why:?}");
+                        continue;
+                    }
+                },
+            };
+
+            // Countdowns which fail to send are retried on the next tick, for a while.
+            if let Some(channel) = channel {
+                let result = ChannelId(channel as u64)
+                    .say(&http, countdown.announcement())
+                    .await;
+                if let Err(why) = result {
+                    warn!("failed to announce countdown: {why:?}");
+                    if now - countdown.end < ANNOUNCE_RETRY_SECS {
+                        continue;
+                    }
+                }
+            }
+
+            if let Err(why) = countdown_store.finish(&countdown).await {
+                warn!("failed to finish countdown: {why:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::models::dice::{parse_repeated, DiceExpr};
+use crate::models::probability::Pmf;
+use anyhow::bail;
+use serenity::client::Context;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+
+/// The most totals shown in a distribution table.
+const MAX_ROWS: usize = 30;
+/// The width of the longest bar in a distribution table.
+const BAR_WIDTH: f64 = 20.;
+/// Discord refuses messages longer than this.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+fn describe_rolls(expr: &DiceExpr, repeats: usize) -> String {
+    // Roll everything up front; the thread's RNG can't be held across an await.
+    let mut rng = rand::thread_rng();
+    let results = (0..repeats)
+        .map(|_| expr.roll(&mut rng))
+        .collect::<Vec<_>>();
+
+    let detailed = results
+        .iter()
+        .map(|result| format!("`{expr}`: {result} = **{}**", result.total()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if detailed.len() <= MAX_MESSAGE_LENGTH {
+        return detailed;
+    }
+
+    // Too many dice to show them all, so just give the totals.
+    let totals = results
+        .iter()
+        .map(|result| format!("**{}**", result.total()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("`{expr}`: {totals}")
+}
+
+fn describe_distribution(expr: &DiceExpr, pmf: &Pmf) -> String {
+    let masses = pmf.masses().collect::<Vec<_>>();
+    let mode = masses
+        .iter()
+        .enumerate()
+        .max_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+        .map_or(0, |(i, _)| i);
+    let highest = masses[mode].1;
+
+    // Show the totals around the most likely one, if there are too many to show them all.
+    let start = mode
+        .saturating_sub(MAX_ROWS / 2)
+        .min(masses.len().saturating_sub(MAX_ROWS));
+    let shown = &masses[start..masses.len().min(start + MAX_ROWS)];
+    let width = pmf.min().to_string().len().max(pmf.max().to_string().len());
+
+    let rows = shown
+        .iter()
+        .map(|(value, mass)| {
+            let bar = "█".repeat((mass / highest * BAR_WIDTH).round() as usize);
+            format!("{value:>width$} {:>7.3}% {bar}", mass * 100.)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut description = format!(
+        "`{expr}`: mean {:.2}, standard deviation {:.2}, range {} to {}\n```\n{rows}\n```",
+        pmf.mean(),
+        pmf.std_dev(),
+        pmf.min(),
+        pmf.max()
+    );
+    if shown.len() < masses.len() {
+        description.push_str(&format!(
+            "Showing the {} most likely totals of {}.",
+            shown.len(),
+            masses.len()
+        ));
+    }
+    description
+}
+
+/// Usage: `~roll stats <expression>`
+async fn stats(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = parse_repeated(args.rest()).and_then(|(expr, repeats)| {
+        if repeats > 1 {
+            bail!("the distribution is the same every time, so leave off the x{repeats}");
+        }
+        Ok(describe_distribution(&expr, &expr.distribution()?))
+    });
+
+    let response = response.unwrap_or_else(|why| format!("{why}"));
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Usage: `~roll <expression> [xN]` or `~roll stats <expression>`
+///
+/// Expressions add and subtract dice and numbers, e.g. `4d6kh3+2`.  Dice can keep (`kh`,
+/// `kl`) or drop (`dh`, `dl`) their highest or lowest rolls, explode (`!`), or be rolled
+/// with advantage (`adv`) or disadvantage (`dis`).
+#[command]
+async fn roll(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.current() == Some("stats") {
+        args.advance();
+        return stats(ctx, msg, args).await;
+    }
+
+    let response = parse_repeated(args.rest())
+        .map(|(expr, repeats)| describe_rolls(&expr, repeats))
+        .unwrap_or_else(|why| format!("{why}"));
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
@@ -1,18 +1,439 @@
-use crate::RockCounterContainer;
+use crate::commands::{invalid_command, is_guild_admin, mentioned_user};
+use crate::models::achievements::AchievementStore;
+use crate::models::loot::DigOutcome;
+use crate::models::rocks::{LeaderboardQuery, RockCount};
+use crate::{AchievementStoreContainer, LootGameContainer, RockCounterContainer};
+use anyhow::Result;
+use chrono::Utc;
 use serenity::framework::standard::{macros::command, Args, CommandResult};
 use serenity::model::channel::Message;
+use serenity::model::id::{GuildId, RoleId};
 use serenity::prelude::*;
+use tracing::warn;
+
+/// How many users `~dig top` lists.
+const LEADERBOARD_SIZE: i64 = 10;
+
+fn format_leaderboard(counts: &[RockCount]) -> String {
+    counts
+        .iter()
+        .enumerate()
+        .map(|(i, rock_count)| {
+            format!(
+                "{}. <@{}> — {}",
+                i + 1,
+                rock_count.user_id,
+                rock_count.count
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Usage: `~dig top [global] [today|week|month]`
+async fn top(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let Ok(query) = LeaderboardQuery::parse(args.rest()) else {
+        return invalid_command(ctx, msg).await;
+    };
+    let guild = msg
+        .guild_id
+        .filter(|_| !query.global)
+        .map(|guild_id| guild_id.0 as i64);
+
+    let data = ctx.data.read().await;
+    let rock_counter = data
+        .get::<RockCounterContainer>()
+        .expect("failed to obtain rock counter");
+
+    let counts = match (guild, query.window) {
+        (_, Some(window)) => {
+            let since = Utc::now().timestamp() - window.seconds();
+            rock_counter
+                .top_since(guild, since, LEADERBOARD_SIZE)
+                .await?
+        }
+        (Some(guild), None) => rock_counter.guild_top(guild, LEADERBOARD_SIZE).await?,
+        (None, None) => rock_counter.top(LEADERBOARD_SIZE).await?,
+    };
+
+    let place = if guild.is_some() {
+        "in this server"
+    } else {
+        "everywhere"
+    };
+    let title = match query.window {
+        Some(window) => format!("Top diggers {place} {}", window.describe()),
+        None => format!("Top diggers {place}"),
+    };
+
+    if counts.is_empty() {
+        let _ = msg.reply(ctx, "Nobody has dug yet.").await;
+        return Ok(());
+    }
+
+    let _ = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title(title);
+                e.description(format_leaderboard(&counts));
+                e
+            });
+
+            m
+        })
+        .await;
+
+    Ok(())
+}
+
+/// Usage: `~dig stats [@user]`
+async fn stats(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let user = mentioned_user(msg, args.rest()).unwrap_or(&msg.author);
+    let user_id = user.id.0 as i64;
+
+    let data = ctx.data.read().await;
+    let rock_counter = data
+        .get::<RockCounterContainer>()
+        .expect("failed to obtain rock counter");
+
+    let count = rock_counter.count(user_id).await?;
+    if count == 0 {
+        let _ = msg
+            .reply(ctx, format!("{} hasn't dug yet.", user.name))
+            .await;
+        return Ok(());
+    }
+    let rank = rock_counter.rank(count).await?;
+    let daily_streak = rock_counter.daily_streak(user_id).await?;
+    let weekly_streak = rock_counter.weekly_streak(user_id).await?;
+
+    let mut guild_stats = None;
+    if let Some(guild_id) = msg.guild_id {
+        let guild_count = rock_counter.guild_count(guild_id.0 as i64, user_id).await?;
+        if guild_count > 0 {
+            let guild_rank = rock_counter
+                .guild_rank(guild_id.0 as i64, guild_count)
+                .await?;
+            guild_stats = Some((guild_count, guild_rank));
+        }
+    }
+
+    let _ = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title(format!("{}'s digging", user.name));
+                e.field("Digs:", count, true);
+                e.field("Global rank:", format!("#{rank}"), true);
+                if let Some((guild_count, guild_rank)) = guild_stats {
+                    e.field("Digs here:", guild_count, true);
+                    e.field("Server rank:", format!("#{guild_rank}"), true);
+                }
+                e.field("Daily streak:", format!("{daily_streak} day(s)"), true);
+                e.field("Weekly streak:", format!("{weekly_streak} week(s)"), true);
+                e
+            });
+
+            m
+        })
+        .await;
+
+    Ok(())
+}
 
 #[command]
-async fn dig(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+async fn dig(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    match args.current() {
+        Some("top") => {
+            args.advance();
+            return top(ctx, msg, args).await;
+        }
+        Some("stats") => {
+            args.advance();
+            return stats(ctx, msg, args).await;
+        }
+        _ => {}
+    }
+
     let data = ctx.data.read().await;
 
+    let loot_game = data
+        .get::<LootGameContainer>()
+        .expect("failed to obtain loot game");
+    let (item, quantity) = match loot_game.dig(msg.author.id.0 as i64).await? {
+        DigOutcome::Found { item, quantity } => (item, quantity),
+        DigOutcome::Cooldown(remaining) => {
+            let _ = msg
+                .reply(
+                    ctx,
+                    format!(
+                        "You're tired from digging, try again in {}s",
+                        remaining.as_secs() + 1
+                    ),
+                )
+                .await;
+            return Ok(());
+        }
+    };
+
     let rock_counter = data
         .get::<RockCounterContainer>()
         .expect("failed to obtain rock counter");
 
-    let count = rock_counter.increment(msg.author.id.0 as i64).await?;
-    let _ = msg.reply(ctx, format!("You have dug {count} times")).await;
+    let guild = msg.guild_id.map(|guild_id| guild_id.0 as i64);
+    let count = rock_counter
+        .increment(msg.author.id.0 as i64, guild, msg.channel_id.0 as i64)
+        .await?;
+
+    // Show the author where they stand in the server they're digging in, or globally in DMs.
+    let rank = match guild {
+        Some(guild) => {
+            let guild_count = rock_counter
+                .guild_count(guild, msg.author.id.0 as i64)
+                .await?;
+            let guild_rank = rock_counter.guild_rank(guild, guild_count).await?;
+            format!("#{guild_rank} in this server")
+        }
+        None => format!("#{} globally", rock_counter.rank(count).await?),
+    };
+
+    let daily_streak = rock_counter.daily_streak(msg.author.id.0 as i64).await?;
+
+    let _ = msg
+        .reply(
+            ctx,
+            format!(
+                "You dug up a {} **{}** ({}, you have {quantity})\nYou have dug {count} times ({rank})",
+                item.emoji, item.name, item.rarity
+            ),
+        )
+        .await;
+
+    let achievement_store = data
+        .get::<AchievementStoreContainer>()
+        .expect("failed to obtain achievement store");
+    for achievement in achievement_store
+        .award(msg.author.id.0 as i64, count, daily_streak)
+        .await?
+    {
+        let _ = msg
+            .channel_id
+            .say(
+                ctx,
+                format!(
+                    ":trophy: {} unlocked **{}**: {}",
+                    msg.author.mention(),
+                    achievement.name,
+                    achievement.description
+                ),
+            )
+            .await;
+    }
+
+    // Checked on every dig rather than only when an achievement is first earned, since it
+    // may have been earned in another guild or before the role was set up.  Each role is
+    // only attempted once, so a deleted role or missing permission doesn't fail every dig.
+    if let Some(guild_id) = msg.guild_id {
+        if let Err(why) = grant_roles(ctx, msg, guild_id, achievement_store).await {
+            warn!("failed to grant achievement roles in guild {guild_id}: {why:?}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Gives the author any roles configured in the given guild for achievements they've earned
+/// which haven't been granted to them yet.
+async fn grant_roles(
+    ctx: &Context,
+    msg: &Message,
+    guild_id: GuildId,
+    achievement_store: &AchievementStore<'_>,
+) -> Result<()> {
+    let held = msg
+        .member
+        .as_ref()
+        .map(|member| member.roles.as_slice())
+        .unwrap_or_default();
+
+    let (guild, user_id) = (guild_id.0 as i64, msg.author.id.0 as i64);
+
+    for reward in achievement_store.pending_roles(guild, user_id).await? {
+        // Adding a role is a plain REST call, so doesn't need the privileged members intent.
+        if !held.contains(&RoleId(reward.role_id as u64)) {
+            if let Err(why) = ctx
+                .http
+                .add_member_role(
+                    guild_id.0,
+                    msg.author.id.0,
+                    reward.role_id as u64,
+                    Some(&format!("Earned the {} achievement", reward.name)),
+                )
+                .await
+            {
+                warn!("failed to grant role for {}: {why:?}", reward.name);
+            }
+        }
+
+        achievement_store
+            .record_grant(guild, user_id, reward.role_id)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Usage: `~achievements role <achievement> <@role|none>`
+async fn set_achievement_role(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let Some(guild_id) = msg.guild_id else {
+        return invalid_command(ctx, msg).await;
+    };
+    if !is_guild_admin(ctx, msg).await {
+        return invalid_command(ctx, msg).await;
+    }
+
+    let Ok(achievement_id) = args.single::<String>() else {
+        return invalid_command(ctx, msg).await;
+    };
+    let role_id: Option<RoleId> = match (msg.mention_roles.first(), args.current()) {
+        (Some(role_id), _) => Some(*role_id),
+        (None, Some("none")) => None,
+        _ => return invalid_command(ctx, msg).await,
+    };
+
+    let data = ctx.data.read().await;
+    let achievement_store = data
+        .get::<AchievementStoreContainer>()
+        .expect("failed to obtain achievement store");
+
+    let definitions = achievement_store.definitions().await?;
+    if !definitions.iter().any(|a| a.id == achievement_id) {
+        let ids: Vec<_> = definitions.iter().map(|a| format!("`{}`", a.id)).collect();
+        let _ = msg
+            .reply(
+                ctx,
+                format!("Unknown achievement, try one of {}", ids.join(", ")),
+            )
+            .await;
+        return Ok(());
+    }
+
+    achievement_store
+        .set_role(
+            guild_id.0 as i64,
+            &achievement_id,
+            role_id.map(|role_id| role_id.0 as i64),
+        )
+        .await?;
+
+    let _ = msg.react(ctx, '👍').await;
+    Ok(())
+}
+
+/// Usage: `~achievements [@user]`
+#[command]
+async fn achievements(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.current() == Some("role") {
+        args.advance();
+        return set_achievement_role(ctx, msg, args).await;
+    }
+
+    let user = mentioned_user(msg, args.rest()).unwrap_or(&msg.author);
+
+    let data = ctx.data.read().await;
+    let achievement_store = data
+        .get::<AchievementStoreContainer>()
+        .expect("failed to obtain achievement store");
+
+    let earned = achievement_store.earned(user.id.0 as i64).await?;
+    let definitions = achievement_store.definitions().await?;
+
+    let description = definitions
+        .iter()
+        .map(|achievement| {
+            let mark = if earned.iter().any(|e| e.id == achievement.id) {
+                ":trophy:"
+            } else {
+                ":lock:"
+            };
+            format!(
+                "{mark} **{}** (`{}`): {}",
+                achievement.name, achievement.id, achievement.description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let _ = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title(format!(
+                    "{}'s achievements ({}/{})",
+                    user.name,
+                    earned.len(),
+                    definitions.len()
+                ));
+                e.description(description);
+                e
+            });
+
+            m
+        })
+        .await;
+
+    Ok(())
+}
+
+/// Usage: `~inventory [@user]`
+#[command]
+async fn inventory(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let user = mentioned_user(msg, args.rest()).unwrap_or(&msg.author);
+
+    let data = ctx.data.read().await;
+    let loot_game = data
+        .get::<LootGameContainer>()
+        .expect("failed to obtain loot game");
+
+    let mut entries: Vec<_> = loot_game
+        .inventory(user.id.0 as i64)
+        .await?
+        .into_iter()
+        .filter_map(|entry| Some((loot_game.table().item(&entry.item)?, entry.quantity)))
+        .collect();
+    if entries.is_empty() {
+        let _ = msg
+            .reply(ctx, format!("{} hasn't dug anything up yet.", user.name))
+            .await;
+        return Ok(());
+    }
+
+    // Show the rarest finds first.
+    entries.sort_by(|(a, _), (b, _)| b.rarity.cmp(&a.rarity).then_with(|| a.name.cmp(&b.name)));
+    let description = entries
+        .iter()
+        .map(|(item, quantity)| {
+            format!(
+                "{} {} × {quantity} ({})",
+                item.emoji, item.name, item.rarity
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let _ = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title(format!("{}'s inventory", user.name));
+                e.description(description);
+                e
+            });
+
+            m
+        })
+        .await;
 
     Ok(())
 }
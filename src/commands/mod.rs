@@ -1,15 +1,22 @@
 use serenity::client::Context;
 use serenity::framework::standard::CommandResult;
 use serenity::model::channel::Message;
+use serenity::model::user::User;
+use serenity::utils::parse_username;
 
 pub(crate) mod animals;
+pub(crate) mod calc;
+pub(crate) mod config;
 pub(crate) mod countdown;
+pub(crate) mod dice;
 pub(crate) mod dig;
 pub(crate) mod help;
 pub(crate) mod mtg;
 pub(crate) mod probability;
 pub(crate) mod quit;
 pub(crate) mod sandboxes;
+pub(crate) mod snippets;
+pub(crate) mod statistics;
 pub(crate) mod weather;
 
 /// Used to react to user commands which are invalid in a fundamental way.
@@ -18,3 +25,23 @@ pub(crate) async fn invalid_command(ctx: &Context, msg: &Message) -> CommandResu
 
     Ok(())
 }
+
+/// Returns whether the author of the message can manage the guild it was sent in.
+pub(crate) async fn is_guild_admin(ctx: &Context, msg: &Message) -> bool {
+    match msg.member(ctx).await {
+        Ok(member) => member
+            .permissions(ctx)
+            .map(|permissions| permissions.manage_guild())
+            .unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Returns the user mentioned by `<@id>` in the given command arguments.
+///
+/// `msg.mentions` alone isn't enough, since replying to a message also mentions its author.
+pub(crate) fn mentioned_user<'a>(msg: &'a Message, args: &str) -> Option<&'a User> {
+    args.split_whitespace()
+        .filter_map(parse_username)
+        .find_map(|id| msg.mentions.iter().find(|user| user.id.0 == id))
+}
@@ -1,24 +1,241 @@
-use crate::commands::invalid_command;
-use crate::models::probability::GaussianDist;
+use crate::models::plots::density_plot;
+use crate::models::probability::Dist;
+use anyhow::{anyhow, bail, Context as _, Result};
 use serenity::client::Context;
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandResult};
-use serenity::model::channel::Message;
+use serenity::model::channel::{AttachmentType, Message};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use tracing::warn;
 
-#[command]
-async fn normalcdf(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let dist = GaussianDist::new();
+/// Decimal places shown unless `dp=` says otherwise.
+const DEFAULT_DP: usize = 4;
+/// How many standard deviations either side of the mean a normal distribution is plotted.
+const PLOT_SIGMAS: f64 = 4.;
 
-    if let Ok(level) = args.single::<f64>() {
-        let response = if let Ok(upper) = args.single::<f64>() {
-            dist.normal_cdf(level, upper)
-        } else {
-            dist.normal_cdf_one_sided(level)
-        };
-        msg.reply(ctx, response).await?;
-    } else {
-        invalid_command(ctx, msg).await?;
+/// The arguments to a probability command: positional values, along with `name=value`
+/// parameters for the distribution and `dp=` for the number of decimal places shown.
+struct ProbabilityArgs {
+    values: Vec<f64>,
+    params: HashMap<String, f64>,
+    dp: usize,
+}
+
+impl ProbabilityArgs {
+    fn parse(args: &Args) -> Result<Self> {
+        let mut values = Vec::new();
+        let mut params = HashMap::new();
+        let mut dp = DEFAULT_DP;
+
+        for arg in args.raw() {
+            match arg.split_once('=') {
+                Some(("dp", value)) => {
+                    dp = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|dp| *dp <= 15)
+                        .ok_or_else(|| anyhow!("dp must be a whole number up to 15"))?;
+                }
+                Some((name, value)) => {
+                    let value = value
+                        .parse()
+                        .with_context(|| format!("{value} isn't a number"))?;
+                    params.insert(name.to_lowercase(), value);
+                }
+                None => values.push(
+                    arg.parse()
+                        .with_context(|| format!("{arg} isn't a number"))?,
+                ),
+            }
+        }
+
+        Ok(Self { values, params, dp })
     }
 
+    /// Returns the parameter known by any of the given names, or the default if it wasn't
+    /// given.
+    fn param(&self, names: &[&str], default: Option<f64>) -> Result<f64> {
+        names
+            .iter()
+            .find_map(|name| self.params.get(*name).copied())
+            .or(default)
+            .ok_or_else(|| anyhow!("{}= is required", names[0]))
+    }
+}
+
+async fn reply(ctx: &Context, msg: &Message, response: Result<String>) -> CommandResult {
+    let response = response.unwrap_or_else(|why| format!("{why}"));
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Describes P(X <= upper), or P(lower <= X <= upper) given two values.
+fn describe_interval(dist: Dist, args: &ProbabilityArgs) -> Result<String> {
+    let dp = args.dp;
+    match args.values.as_slice() {
+        [upper] => Ok(format!(
+            "P(X ≤ {upper}) = {:.dp$} where X ~ {dist}",
+            dist.cdf(*upper)
+        )),
+        [lower, upper] => Ok(format!(
+            "P({lower} ≤ X ≤ {upper}) = {:.dp$} where X ~ {dist}",
+            dist.interval(*lower, *upper)
+        )),
+        _ => bail!("give an upper bound, or a lower and upper bound"),
+    }
+}
+
+/// Describes P(X = k) for a discrete distribution.
+fn describe_mass(dist: Dist, args: &ProbabilityArgs) -> Result<String> {
+    let dp = args.dp;
+    match args.values.as_slice() {
+        [k] if dist.is_discrete() => Ok(format!(
+            "P(X = {k}) = {:.dp$} where X ~ {dist}",
+            dist.pmf(*k).unwrap_or_default()
+        )),
+        _ => bail!("give a single value"),
+    }
+}
+
+/// Plots the density of a normal distribution, shading the area a `~normalcdf` asked for.
+fn plot_normal_interval(mu: f64, sigma: f64, args: &ProbabilityArgs) -> Result<Vec<u8>> {
+    let dist = Dist::normal(mu, sigma)?;
+    let (lower, upper) = match args.values.as_slice() {
+        [upper] => (f64::NEG_INFINITY, *upper),
+        [lower, upper] => (*lower, *upper),
+        _ => bail!("give an upper bound, or a lower and upper bound"),
+    };
+
+    density_plot(
+        |x| dist.density(x).unwrap_or_default(),
+        (mu - PLOT_SIGMAS * sigma, mu + PLOT_SIGMAS * sigma),
+        (lower, upper),
+    )
+}
+
+/// Usage: `~normalcdf [lower] upper [mu=0] [sigma=1] [dp=4]`
+#[command]
+async fn normalcdf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let mu = args.param(&["mu", "μ", "mean"], Some(0.))?;
+        let sigma = args.param(&["sigma", "σ", "sd"], Some(1.))?;
+        let description = describe_interval(Dist::normal(mu, sigma)?, &args)?;
+
+        // The answer is still worth sending if the plot can't be drawn.
+        let plot = plot_normal_interval(mu, sigma, &args)
+            .map_err(|why| warn!("failed to plot normal distribution: {why:?}"))
+            .ok();
+        Ok((description, plot))
+    });
+
+    let (description, plot) = match response {
+        Ok(response) => response,
+        Err(why) => return reply(ctx, msg, Err(why)).await,
+    };
+    let Some(plot) = plot else {
+        return reply(ctx, msg, Ok(description)).await;
+    };
+
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.content(description)
+                .reference_message(msg)
+                .add_file(AttachmentType::Bytes {
+                    data: Cow::Owned(plot),
+                    filename: "normalcdf.png".to_string(),
+                })
+        })
+        .await?;
     Ok(())
 }
+
+/// Usage: `~invnorm p [mu=0] [sigma=1] [dp=4]`
+#[command]
+async fn invnorm(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let dist = Dist::normal(
+            args.param(&["mu", "μ", "mean"], Some(0.))?,
+            args.param(&["sigma", "σ", "sd"], Some(1.))?,
+        )?;
+        let [p] = args.values.as_slice() else {
+            bail!("give a single probability");
+        };
+        let dp = args.dp;
+        Ok(format!(
+            "P(X ≤ {:.dp$}) = {p} where X ~ {dist}",
+            dist.inverse(*p)?
+        ))
+    });
+    reply(ctx, msg, response).await
+}
+
+/// Usage: `~binompdf n=<trials> p=<probability> k [dp=4]`
+#[command]
+async fn binompdf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let dist = Dist::binomial(args.param(&["n"], None)?, args.param(&["p"], None)?)?;
+        describe_mass(dist, &args)
+    });
+    reply(ctx, msg, response).await
+}
+
+/// Usage: `~binomcdf n=<trials> p=<probability> [lower] upper [dp=4]`
+#[command]
+async fn binomcdf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let dist = Dist::binomial(args.param(&["n"], None)?, args.param(&["p"], None)?)?;
+        describe_interval(dist, &args)
+    });
+    reply(ctx, msg, response).await
+}
+
+/// Usage: `~poissonpdf lambda=<rate> k [dp=4]`
+#[command]
+async fn poissonpdf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let dist = Dist::poisson(args.param(&["lambda", "λ"], None)?)?;
+        describe_mass(dist, &args)
+    });
+    reply(ctx, msg, response).await
+}
+
+/// Usage: `~poissoncdf lambda=<rate> [lower] upper [dp=4]`
+#[command]
+async fn poissoncdf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let dist = Dist::poisson(args.param(&["lambda", "λ"], None)?)?;
+        describe_interval(dist, &args)
+    });
+    reply(ctx, msg, response).await
+}
+
+/// Usage: `~tcdf df=<degrees of freedom> [lower] upper [dp=4]`
+#[command]
+async fn tcdf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let dist = Dist::student_t(args.param(&["df", "ν"], None)?)?;
+        describe_interval(dist, &args)
+    });
+    reply(ctx, msg, response).await
+}
+
+/// Usage: `~chisqcdf df=<degrees of freedom> [lower] upper [dp=4]`
+#[command]
+async fn chisqcdf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let dist = Dist::chi_squared(args.param(&["df", "k"], None)?)?;
+        describe_interval(dist, &args)
+    });
+    reply(ctx, msg, response).await
+}
+
+/// Usage: `~expcdf lambda=<rate> [lower] upper [dp=4]`
+#[command]
+async fn expcdf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = ProbabilityArgs::parse(&args).and_then(|args| {
+        let dist = Dist::exponential(args.param(&["lambda", "λ"], None)?)?;
+        describe_interval(dist, &args)
+    });
+    reply(ctx, msg, response).await
+}
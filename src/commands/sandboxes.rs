@@ -1,46 +1,170 @@
-use crate::models::sandboxes::{run_python_code, run_rust_code};
+use crate::commands::config::prefix_for;
+use crate::models::sandboxes::{wrap_python, wrap_rust, Language, TrackedReply};
+use crate::{SandboxReplyCacheContainer, SandboxRunnerContainer};
+use anyhow::Result;
 use serenity::client::Context;
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandResult};
 use serenity::model::channel::Message;
+use serenity::model::event::MessageUpdateEvent;
+use serenity::model::id::{ChannelId, MessageId};
 
-#[command]
-async fn rust(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let code = format!(
-        "fn main() {{\nprintln!(\"{{:?}}\", {{ {} }} );\n}}",
-        args.rest()
-    );
-    let result = run_rust_code(code).await?;
-    if !result.is_empty() {
-        msg.reply(ctx, result).await?;
+/// Wraps user input into a complete program, unless it is to be run as-is.
+fn prepare_code(language: Language, raw: bool, input: &str) -> String {
+    match (language, raw) {
+        (_, true) => input.to_string(),
+        (Language::Rust, false) => wrap_rust(input),
+        (Language::Python, false) => wrap_python(input.trim()),
+    }
+}
+
+/// Runs the given code in a sandbox on behalf of `user_id`, returning the text to reply with.
+async fn run_for_user(
+    ctx: &Context,
+    user_id: u64,
+    language: Language,
+    code: String,
+    on_queued: impl FnOnce(usize),
+) -> Result<String> {
+    let data = ctx.data.read().await;
+    let runner = data
+        .get::<SandboxRunnerContainer>()
+        .expect("failed to obtain sandbox runner");
+
+    let ticket = match runner.enqueue(user_id) {
+        Ok(ticket) => ticket,
+        Err(rejection) => return Ok(rejection.to_string()),
+    };
+
+    if ticket.position() > 0 {
+        on_queued(ticket.position());
+    }
+
+    ticket.run(language, code).await
+}
+
+/// Runs the given input in a sandbox on behalf of the message author and replies with the
+/// output, returning the reply (if one was sent).
+pub(crate) async fn run_and_reply(
+    ctx: &Context,
+    msg: &Message,
+    language: Language,
+    raw: bool,
+    input: &str,
+) -> Result<Option<Message>> {
+    let code = prepare_code(language, raw, input);
+    let result = run_for_user(ctx, msg.author.id.0, language, code, |position| {
+        let (ctx, msg) = (ctx.clone(), msg.clone());
+        tokio::spawn(async move {
+            let _ = msg
+                .reply(ctx, format!("Queued at position {position}, hang tight!"))
+                .await;
+        });
+    })
+    .await?;
+
+    if result.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(msg.reply(ctx, result).await?))
+}
+
+/// Runs the user's code in a sandbox and replies with the output, remembering the reply
+/// so that it can be updated if the triggering message is edited.
+async fn execute(
+    ctx: &Context,
+    msg: &Message,
+    language: Language,
+    raw: bool,
+    args: Args,
+) -> CommandResult {
+    if let Some(reply) = run_and_reply(ctx, msg, language, raw, args.rest()).await? {
+        let data = ctx.data.read().await;
+        data.get::<SandboxReplyCacheContainer>()
+            .expect("failed to obtain sandbox reply cache")
+            .insert(
+                msg.id.0,
+                TrackedReply {
+                    language,
+                    raw,
+                    channel_id: reply.channel_id.0,
+                    reply_id: reply.id.0,
+                },
+            );
     }
     Ok(())
 }
 
-#[command]
-async fn rust_raw(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let result = run_rust_code(args.rest().to_string()).await?;
-    if !result.is_empty() {
-        msg.reply(ctx, result).await?;
+/// Re-runs an edited sandbox invocation and updates the bot's original reply in place.
+pub(crate) async fn rerun_edited(ctx: &Context, event: &MessageUpdateEvent) -> Result<()> {
+    let tracked = {
+        let data = ctx.data.read().await;
+        data.get::<SandboxReplyCacheContainer>()
+            .expect("failed to obtain sandbox reply cache")
+            .get(event.id.0)
+    };
+
+    let (Some(tracked), Some(content), Some(author)) = (tracked, &event.content, &event.author)
+    else {
+        return Ok(());
+    };
+
+    // Only re-run messages which still invoke the same command, and otherwise stop
+    // tracking the reply.
+    let prefix = prefix_for(ctx, event.guild_id).await;
+    let Some(input) = tracked.code_from(content, &prefix) else {
+        let data = ctx.data.read().await;
+        data.get::<SandboxReplyCacheContainer>()
+            .expect("failed to obtain sandbox reply cache")
+            .remove(event.id.0);
+        return Ok(());
+    };
+
+    let code = prepare_code(tracked.language, tracked.raw, input);
+    let mut result = run_for_user(ctx, author.id.0, tracked.language, code, |_| {}).await?;
+    if result.is_empty() {
+        result = String::from("(no output)");
     }
+
+    ChannelId(tracked.channel_id)
+        .edit_message(ctx, MessageId(tracked.reply_id), |m| m.content(result))
+        .await?;
     Ok(())
 }
 
-#[command]
-async fn py(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let code = format!("print({})", args.rest().trim());
-    let result = run_python_code(code).await?;
-    if !result.is_empty() {
-        msg.reply(ctx, result).await?;
+/// Deletes the bot's reply to a sandbox invocation whose triggering message was deleted.
+pub(crate) async fn delete_reply(ctx: &Context, deleted_message_id: MessageId) -> Result<()> {
+    let tracked = {
+        let data = ctx.data.read().await;
+        data.get::<SandboxReplyCacheContainer>()
+            .expect("failed to obtain sandbox reply cache")
+            .remove(deleted_message_id.0)
+    };
+
+    if let Some(tracked) = tracked {
+        ChannelId(tracked.channel_id)
+            .delete_message(ctx, MessageId(tracked.reply_id))
+            .await?;
     }
     Ok(())
 }
 
+#[command]
+async fn rust(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    execute(ctx, msg, Language::Rust, false, args).await
+}
+
+#[command]
+async fn rust_raw(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    execute(ctx, msg, Language::Rust, true, args).await
+}
+
+#[command]
+async fn py(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    execute(ctx, msg, Language::Python, false, args).await
+}
+
 #[command]
 async fn py_raw(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let result = run_python_code(args.rest().to_string()).await?;
-    if !result.is_empty() {
-        msg.reply(ctx, result).await?;
-    }
-    Ok(())
+    execute(ctx, msg, Language::Python, true, args).await
 }
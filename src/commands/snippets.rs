@@ -0,0 +1,170 @@
+use crate::commands::sandboxes::run_and_reply;
+use crate::commands::{invalid_command, mentioned_user};
+use crate::models::sandboxes::Language;
+use crate::SnippetStoreContainer;
+use serenity::client::Context;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Strips a surrounding markdown code block from the given code, returning the language
+/// tag of the block (if any) along with its contents.
+fn strip_code_block(code: &str) -> (Option<&str>, &str) {
+    let code = code.trim();
+
+    match code
+        .strip_prefix("```")
+        .and_then(|inner| inner.strip_suffix("```"))
+    {
+        Some(inner) => match inner.split_once('\n') {
+            Some((tag, body)) if !tag.trim().is_empty() => (Some(tag.trim()), body),
+            Some((_, body)) => (None, body),
+            None => (None, inner),
+        },
+        None => (None, code),
+    }
+}
+
+async fn save_snippet(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = match args.single::<String>() {
+        Ok(name) if name.len() <= MAX_NAME_LENGTH => name,
+        _ => return invalid_command(ctx, msg).await,
+    };
+
+    // The language can either be given explicitly, or taken from the code block.
+    let explicit_language = args.parse::<Language>().ok();
+    if explicit_language.is_some() {
+        args.advance();
+    }
+
+    let (tag, code) = strip_code_block(args.rest());
+    let language = explicit_language.or_else(|| tag.and_then(|tag| tag.parse().ok()));
+
+    let Some(language) = language else {
+        let _ = msg
+            .reply(
+                ctx,
+                "Which language is that? Try `~snippet save <name> rust|py <code>`",
+            )
+            .await;
+        return Ok(());
+    };
+    if code.trim().is_empty() {
+        return invalid_command(ctx, msg).await;
+    }
+
+    let data = ctx.data.read().await;
+    let snippet_store = data
+        .get::<SnippetStoreContainer>()
+        .expect("failed to obtain snippet store");
+
+    snippet_store
+        .save(msg.author.id.0 as i64, &name, language, code)
+        .await?;
+    let _ = msg.react(ctx, '👍').await;
+
+    Ok(())
+}
+
+async fn run_snippet(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let Ok(name) = args.single::<String>() else {
+        return invalid_command(ctx, msg).await;
+    };
+
+    let snippet = {
+        let data = ctx.data.read().await;
+        let snippet_store = data
+            .get::<SnippetStoreContainer>()
+            .expect("failed to obtain snippet store");
+
+        snippet_store.get(msg.author.id.0 as i64, &name).await?
+    };
+
+    match snippet {
+        Some(snippet) => {
+            run_and_reply(ctx, msg, snippet.language()?, false, &snippet.code).await?;
+        }
+        None => {
+            let _ = msg
+                .reply(ctx, format!("You don't have a snippet called `{name}`"))
+                .await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn list_snippets(ctx: &Context, msg: &Message) -> CommandResult {
+    let data = ctx.data.read().await;
+    let snippet_store = data
+        .get::<SnippetStoreContainer>()
+        .expect("failed to obtain snippet store");
+
+    let snippets: Vec<_> = snippet_store
+        .list(msg.author.id.0 as i64)
+        .await?
+        .into_iter()
+        .map(|snippet| format!("  - `{}` ({})", snippet.name, snippet.language))
+        .collect();
+
+    let response = if snippets.is_empty() {
+        String::from("You don't have any saved snippets!")
+    } else {
+        format!("Your snippets:\n{}", snippets.join("\n"))
+    };
+
+    let _ = msg.reply(ctx, response).await;
+    Ok(())
+}
+
+async fn share_snippet(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>();
+    let (Ok(name), Some(recipient)) = (name, mentioned_user(msg, args.rest())) else {
+        return invalid_command(ctx, msg).await;
+    };
+    if recipient.id == msg.author.id || recipient.bot {
+        return invalid_command(ctx, msg).await;
+    }
+
+    let data = ctx.data.read().await;
+    let snippet_store = data
+        .get::<SnippetStoreContainer>()
+        .expect("failed to obtain snippet store");
+
+    let Some(snippet) = snippet_store.get(msg.author.id.0 as i64, &name).await? else {
+        let _ = msg
+            .reply(ctx, format!("You don't have a snippet called `{name}`"))
+            .await;
+        return Ok(());
+    };
+
+    if snippet_store.share(&snippet, recipient.id.0 as i64).await? {
+        let _ = msg.react(ctx, '👍').await;
+    } else {
+        let _ = msg
+            .reply(
+                ctx,
+                format!("{} already has a snippet called `{name}`", recipient.name),
+            )
+            .await;
+    }
+
+    Ok(())
+}
+
+#[command]
+async fn snippet(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if let Ok(mode) = args.single::<String>() {
+        match mode.as_str() {
+            "save" => save_snippet(ctx, msg, args).await,
+            "run" => run_snippet(ctx, msg, args).await,
+            "list" => list_snippets(ctx, msg).await,
+            "share" => share_snippet(ctx, msg, args).await,
+            _ => invalid_command(ctx, msg).await,
+        }
+    } else {
+        invalid_command(ctx, msg).await
+    }
+}
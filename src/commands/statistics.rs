@@ -0,0 +1,193 @@
+use crate::models::statistics::{parse_csv, Summary};
+use anyhow::{bail, Context as _, Result};
+use serenity::client::Context;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+
+/// The largest attachment that will be read.
+const MAX_ATTACHMENT_SIZE: u64 = 1024 * 1024;
+/// The width of the box plot, in characters.
+const BOX_PLOT_WIDTH: usize = 40;
+/// The width of the longest bar in the histogram.
+const BAR_WIDTH: usize = 20;
+/// The most modes listed before the rest are left out.
+const MAX_MODES: usize = 5;
+
+/// The arguments to `~stats`: numbers, along with `column=`, `ci=` and `mu=` options.
+struct StatsArgs {
+    values: Vec<f64>,
+    column: Option<String>,
+    confidence: f64,
+    mu: Option<f64>,
+}
+
+impl StatsArgs {
+    fn parse(args: &Args) -> Result<Self> {
+        let mut values = Vec::new();
+        let mut column = None;
+        let mut confidence = 0.95;
+        let mut mu = None;
+
+        for arg in args.raw() {
+            match arg.split_once('=') {
+                Some(("column", value)) => column = Some(value.to_string()),
+                Some(("ci", value)) => {
+                    let level = value
+                        .trim_end_matches('%')
+                        .parse::<f64>()
+                        .with_context(|| format!("{value} isn't a confidence level"))?;
+                    // Accept both ci=95 and ci=0.95
+                    confidence = if level > 1. { level / 100. } else { level };
+                    if confidence.is_nan() || confidence <= 0. || confidence >= 1. {
+                        bail!("the confidence level must be between 0% and 100%");
+                    }
+                }
+                Some(("mu" | "μ", value)) => {
+                    mu = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("{value} isn't a number"))?,
+                    );
+                }
+                Some((name, _)) => bail!("unknown option {name}"),
+                None => {
+                    for value in arg.split(',').filter(|value| !value.is_empty()) {
+                        values.push(
+                            value
+                                .parse()
+                                .with_context(|| format!("{value} isn't a number"))?,
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            values,
+            column,
+            confidence,
+            mu,
+        })
+    }
+}
+
+/// Formats a value to at most four decimal places.
+fn format_value(value: f64) -> String {
+    let formatted = format!("{value:.4}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn describe(summary: &Summary, args: &StatsArgs) -> Result<String> {
+    let modes = match summary.modes.len() {
+        0 => "none".to_string(),
+        n => {
+            let mut modes = summary.modes[..n.min(MAX_MODES)]
+                .iter()
+                .map(|mode| format_value(*mode))
+                .collect::<Vec<_>>();
+            if n > MAX_MODES {
+                modes.push(format!("and {} more", n - MAX_MODES));
+            }
+            modes.join(", ")
+        }
+    };
+
+    let mut lines = vec![
+        format!(
+            "n = {}   mean = {}   median = {}   mode = {modes}",
+            summary.count(),
+            format_value(summary.mean),
+            format_value(summary.median)
+        ),
+        format!(
+            "sd = {}   min = {}   Q1 = {}   Q3 = {}   max = {}",
+            summary.std_dev.map_or("n/a".to_string(), format_value),
+            format_value(summary.min()),
+            format_value(summary.q1),
+            format_value(summary.q3),
+            format_value(summary.max())
+        ),
+    ];
+
+    if summary.std_dev.is_some() {
+        let (lower, upper) = summary.mean_interval(args.confidence)?;
+        lines.push(format!(
+            "{}% CI for the mean: {} to {}",
+            format_value(args.confidence * 100.),
+            format_value(lower),
+            format_value(upper)
+        ));
+    }
+    if let Some(mu) = args.mu {
+        let test = summary.t_test(mu)?;
+        lines.push(format!(
+            "t-test against μ = {}: t = {}, df = {}, p = {}",
+            format_value(mu),
+            format_value(test.t),
+            test.df,
+            format_value(test.p)
+        ));
+    }
+
+    let histogram = summary.histogram();
+    let highest = histogram.iter().map(|(_, count)| *count).max().unwrap_or(1);
+    let labels = histogram
+        .iter()
+        .map(|(lower, _)| format_value(*lower))
+        .collect::<Vec<_>>();
+    let width = labels.iter().map(String::len).max().unwrap_or(0);
+    lines.push(String::new());
+    for (label, (_, count)) in labels.iter().zip(&histogram) {
+        let bar = "#".repeat((count * BAR_WIDTH).div_ceil(highest));
+        lines.push(format!("{label:>width$} | {bar} {count}"));
+    }
+
+    lines.push(String::new());
+    lines.push(format!(
+        "{} {} {}",
+        format_value(summary.min()),
+        summary.box_plot(BOX_PLOT_WIDTH),
+        format_value(summary.max())
+    ));
+
+    Ok(format!("```\n{}\n```", lines.join("\n")))
+}
+
+async fn summarise(msg: &Message, args: &Args) -> Result<String> {
+    let mut args = StatsArgs::parse(args)?;
+
+    let values = match msg.attachments.first() {
+        Some(_) if !args.values.is_empty() => {
+            bail!("give either some numbers or a CSV attachment, not both")
+        }
+        Some(attachment) if attachment.size > MAX_ATTACHMENT_SIZE => {
+            bail!("attachments can be at most {MAX_ATTACHMENT_SIZE} bytes")
+        }
+        Some(attachment) => {
+            let bytes = attachment
+                .download()
+                .await
+                .with_context(|| "failed to download the attachment")?;
+            let csv = String::from_utf8(bytes).with_context(|| "the attachment isn't text")?;
+            parse_csv(&csv, args.column.as_deref())?
+        }
+        None => std::mem::take(&mut args.values),
+    };
+
+    describe(&Summary::new(values)?, &args)
+}
+
+/// Usage: `~stats <numbers...> [ci=95] [mu=<value>]`, or with a CSV attachment,
+/// `~stats [column=<name or number>] [ci=95] [mu=<value>]`
+#[command]
+async fn stats(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = summarise(msg, &args)
+        .await
+        .unwrap_or_else(|why| format!("{why}"));
+    let _ = msg.reply(ctx, response).await;
+    Ok(())
+}
@@ -1,43 +1,692 @@
+use anyhow::Result;
+use chrono::Utc;
+use serenity::builder::CreateEmbed;
 use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::http::Http;
 use serenity::model::prelude::*;
 use serenity::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
 
-use crate::commands::invalid_command;
-use crate::{NominatimClientContainer, OpenWeatherMapClientContainer};
+use crate::commands::{invalid_command, is_guild_admin, mentioned_user};
+use crate::models::calc::units::{
+    CELSIUS, FAHRENHEIT, KILOMETRES_PER_HOUR, METRES_PER_SECOND, MILES_PER_HOUR,
+};
+use crate::models::preferences::Units;
+use crate::models::weather::air_quality::{uv_category, AirQuality};
+use crate::models::weather::alerts::{
+    AlertScheduler, AlertSource, AlertSubscription, WeatherAlert,
+};
+use crate::models::weather::{compass_point, local_time, CurrentWeather, WeatherError};
+use crate::{
+    AlertStoreContainer, NominatimClientContainer, OpenWeatherMapClientContainer,
+    PreferenceStoreContainer, WeatherProviderContainer,
+};
 
-#[command]
-async fn weather(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+/// Number of hours covered by `~weather <place> hourly`.
+const HOURLY_HOURS: i64 = 12;
+
+fn format_temp(temp: f32, units: Units) -> String {
+    let fahrenheit = CELSIUS.convert(temp.into(), &FAHRENHEIT);
+    match units {
+        Units::Metric => format!("{temp:.1}°C"),
+        Units::Imperial => format!("{fahrenheit:.1}°F"),
+        Units::Both => format!("{temp:.1}°C / {fahrenheit:.1}°F"),
+    }
+}
+
+fn format_temp_range(min: f32, max: f32, units: Units) -> String {
+    let min_fahrenheit = CELSIUS.convert(min.into(), &FAHRENHEIT);
+    let max_fahrenheit = CELSIUS.convert(max.into(), &FAHRENHEIT);
+    match units {
+        Units::Metric => format!("{min:.0}°C – {max:.0}°C"),
+        Units::Imperial => format!("{min_fahrenheit:.0}°F – {max_fahrenheit:.0}°F"),
+        Units::Both => {
+            format!("{min:.0}°C – {max:.0}°C\n{min_fahrenheit:.0}°F – {max_fahrenheit:.0}°F")
+        }
+    }
+}
+
+/// Formats a speed given in metres per second.
+fn format_speed(speed: f32, units: Units) -> String {
+    let kmh = METRES_PER_SECOND.convert(speed.into(), &KILOMETRES_PER_HOUR);
+    let mph = METRES_PER_SECOND.convert(speed.into(), &MILES_PER_HOUR);
+    match units {
+        Units::Metric => format!("{kmh:.1} km/h"),
+        Units::Imperial => format!("{mph:.1} mph"),
+        Units::Both => format!("{kmh:.1} km/h / {mph:.1} mph"),
+    }
+}
+
+pub fn embed_weather(
+    e: &mut CreateEmbed,
+    display_name: &str,
+    weather: &CurrentWeather,
+    units: Units,
+) {
+    e.title(format!("{} Currently in {display_name}", weather.emoji));
+    e.description(&weather.description);
+    if let Some(icon_url) = &weather.icon_url {
+        e.thumbnail(icon_url);
+    }
+
+    e.field("Temperature:", format_temp(weather.temp, units), true);
+    e.field("Feels like:", format_temp(weather.feels_like, units), true);
+    e.field("Humidity:", format!("{:.0}%", weather.humidity), true);
+    e.field("Pressure:", format!("{:.0} hPa", weather.pressure), true);
+    e.field(
+        "Wind:",
+        format!(
+            "{} {}",
+            format_speed(weather.wind_speed, units),
+            compass_point(weather.wind_deg)
+        ),
+        true,
+    );
+    e.field("Cloud cover:", format!("{:.0}%", weather.cloud_cover), true);
+
+    if let Some(visibility) = weather.visibility {
+        let visibility = match units {
+            Units::Imperial => format!("{:.1} mi", visibility / 1609.34),
+            _ => format!("{:.1} km", visibility / 1000.),
+        };
+        e.field("Visibility:", visibility, true);
+    }
+
+    if let Some(uvi) = weather.uv_index {
+        e.field(
+            "UV index:",
+            format!("{uvi:.1} ({})", uv_category(uvi)),
+            true,
+        );
+    }
+
+    // Sunrise, sunset and the current time are all shown in the location's own timezone.
+    let timezone = weather.utc_offset;
+    if let Some(sunrise) = weather
+        .sunrise
+        .and_then(|sunrise| local_time(sunrise, timezone))
+    {
+        e.field("Sunrise:", sunrise.format("%H:%M"), true);
+    }
+    if let Some(sunset) = weather
+        .sunset
+        .and_then(|sunset| local_time(sunset, timezone))
+    {
+        e.field("Sunset:", sunset.format("%H:%M"), true);
+    }
+    if let Some(now) = local_time(Utc::now().timestamp(), timezone) {
+        e.footer(|ef| {
+            ef.text(format!("Local time: {}", now.format("%H:%M (UTC%:z)")));
+            ef
+        });
+    }
+}
+
+/// Lets the user know why their weather lookup failed.
+async fn reply_with_error(ctx: &Context, msg: &Message, error: WeatherError) {
+    if !matches!(error, WeatherError::NotFound) {
+        warn!("weather lookup failed: {error:?}");
+    }
+
+    let _ = msg.reply(ctx, error.to_string()).await;
+}
+
+/// Lets the user know that weather lookups aren't available.
+async fn reply_not_configured(ctx: &Context, msg: &Message) -> CommandResult {
+    let _ = msg
+        .reply(ctx, "Weather isn't configured for this bot.")
+        .await;
+    Ok(())
+}
+
+/// Splits a trailing `hourly` off the given query, if present.
+fn split_hourly(query: &str) -> (&str, bool) {
+    match query.trim().rsplit_once(char::is_whitespace) {
+        Some((place, "hourly")) => (place.trim(), true),
+        _ if query.trim() == "hourly" => ("", true),
+        _ => (query.trim(), false),
+    }
+}
+
+/// Works out which location the user is asking about, along with the units to reply in.
+///
+/// That is the shared location of a mentioned user, the place in the query, or failing
+/// both of those the author's own saved location.  Replies to the user and returns `None`
+/// if no location could be found.
+async fn resolve_location(
+    ctx: &Context,
+    msg: &Message,
+    place: &str,
+) -> Result<Option<((String, String, String), Units)>> {
+    let data = ctx.data.read().await;
+    let preference_store = data
+        .get::<PreferenceStoreContainer>()
+        .expect("failed to obtain preference store");
+
+    let preferences = preference_store.get(msg.author.id.0 as i64).await?;
+    let units = preferences
+        .as_ref()
+        .map(|preferences| preferences.units())
+        .unwrap_or_default();
+
+    if let Some(user) = mentioned_user(msg, place) {
+        let shared = preference_store
+            .get(user.id.0 as i64)
+            .await?
+            .filter(|preferences| preferences.share_location)
+            .and_then(|preferences| preferences.location());
+
+        if shared.is_none() {
+            let _ = msg
+                .reply(ctx, format!("{} hasn't shared their location.", user.name))
+                .await;
+        }
+        return Ok(shared.map(|location| (location, units)));
+    }
+
+    if place.is_empty() {
+        let saved = preferences.and_then(|preferences| preferences.location());
+        if saved.is_none() {
+            let _ = msg
+                .reply(
+                    ctx,
+                    "You don't have a saved location, set one with `~weather set <place>`",
+                )
+                .await;
+        }
+        return Ok(saved.map(|location| (location, units)));
+    }
+
+    let nominatim_client = data
+        .get::<NominatimClientContainer>()
+        .expect("failed to obtain nominatim client");
+
+    match nominatim_client.search(place).await {
+        Ok(Some(location)) => Ok(Some((location, units))),
+        Ok(None) => {
+            reply_with_error(ctx, msg, WeatherError::NotFound).await;
+            Ok(None)
+        }
+        Err(why) => {
+            reply_with_error(ctx, msg, why).await;
+            Ok(None)
+        }
+    }
+}
+
+async fn set_location(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
     let data = ctx.data.read().await;
     let nominatim_client = data
         .get::<NominatimClientContainer>()
         .expect("failed to obtain nominatim client");
 
-    if let Ok(Some((display_name, lat, lon))) = nominatim_client.search(args.rest()).await {
-        // Now look up the weather at this lat/lon.
-        let owm_client = data
-            .get::<OpenWeatherMapClientContainer>()
-            .expect("failed to open OpenWeatherMap client");
-
-        if let Ok(Some((response, emoji))) = owm_client.get(&lat, &lon).await {
-            msg.reply(
-                ctx,
-                format!(
-                    "Currently in {}: {} **{:.2}°C / {:.2}°F**",
-                    display_name,
-                    emoji,
-                    response.main.temp,
-                    c_to_f(response.main.temp)
-                ),
-            )
-            .await?;
-        }
-    } else {
+    let (display_name, lat, lon) = match nominatim_client.search(args.rest()).await {
+        Ok(Some(location)) => location,
+        Ok(None) => {
+            reply_with_error(ctx, msg, WeatherError::NotFound).await;
+            return Ok(());
+        }
+        Err(why) => {
+            reply_with_error(ctx, msg, why).await;
+            return Ok(());
+        }
+    };
+
+    let preference_store = data
+        .get::<PreferenceStoreContainer>()
+        .expect("failed to obtain preference store");
+    preference_store
+        .set_location(msg.author.id.0 as i64, &display_name, &lat, &lon)
+        .await?;
+
+    let _ = msg
+        .reply(ctx, format!("Your location is now {display_name}"))
+        .await;
+    Ok(())
+}
+
+async fn set_units(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let Ok(units) = args.parse::<Units>() else {
+        return invalid_command(ctx, msg).await;
+    };
+
+    let data = ctx.data.read().await;
+    let preference_store = data
+        .get::<PreferenceStoreContainer>()
+        .expect("failed to obtain preference store");
+    preference_store
+        .set_units(msg.author.id.0 as i64, units)
+        .await?;
+
+    let _ = msg.react(ctx, '👍').await;
+    Ok(())
+}
+
+async fn set_share_location(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let share_location = match args.current() {
+        Some("on") => true,
+        Some("off") => false,
+        _ => return invalid_command(ctx, msg).await,
+    };
+
+    let data = ctx.data.read().await;
+    let preference_store = data
+        .get::<PreferenceStoreContainer>()
+        .expect("failed to obtain preference store");
+    preference_store
+        .set_share_location(msg.author.id.0 as i64, share_location)
+        .await?;
+
+    let _ = msg.react(ctx, '👍').await;
+    Ok(())
+}
+
+fn embed_alert(e: &mut CreateEmbed, subscription: &AlertSubscription, alert: &WeatherAlert) {
+    e.title(format!(":warning: {}", alert.event));
+    e.description(alert.description.chars().take(4000).collect::<String>());
+    e.field("From:", format!("<t:{}:f>", alert.start), true);
+    e.field("Until:", format!("<t:{}:f>", alert.end), true);
+    e.footer(|ef| {
+        ef.text(format!(
+            "{} • {}",
+            alert.sender_name, subscription.location_name
+        ));
+        ef
+    });
+}
+
+async fn alerts(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let Some(guild_id) = msg.guild_id else {
+        return invalid_command(ctx, msg).await;
+    };
+    if !is_guild_admin(ctx, msg).await {
         return invalid_command(ctx, msg).await;
     }
 
+    let data = ctx.data.read().await;
+    let alert_store = data
+        .get::<AlertStoreContainer>()
+        .expect("failed to obtain alert store");
+
+    match args.single::<String>().ok().as_deref() {
+        Some("subscribe") => {
+            let has_one_call = data
+                .get::<OpenWeatherMapClientContainer>()
+                .is_some_and(|owm_client| owm_client.has_one_call());
+            if !has_one_call {
+                let _ = msg
+                    .reply(
+                        ctx,
+                        "Weather alerts need an OpenWeatherMap API key with a One Call 3.0 \
+                         subscription, which isn't set up.",
+                    )
+                    .await;
+                return Ok(());
+            }
+
+            let nominatim_client = data
+                .get::<NominatimClientContainer>()
+                .expect("failed to obtain nominatim client");
+
+            let (display_name, lat, lon) = match nominatim_client.search(args.rest()).await {
+                Ok(Some(location)) => location,
+                Ok(None) => {
+                    reply_with_error(ctx, msg, WeatherError::NotFound).await;
+                    return Ok(());
+                }
+                Err(why) => {
+                    reply_with_error(ctx, msg, why).await;
+                    return Ok(());
+                }
+            };
+
+            let subscribed = alert_store
+                .subscribe(
+                    guild_id.0 as i64,
+                    msg.channel_id.0 as i64,
+                    &display_name,
+                    &lat,
+                    &lon,
+                )
+                .await?;
+            let response = if subscribed {
+                format!("This channel will now receive weather alerts for {display_name}")
+            } else {
+                format!("This channel already receives weather alerts for {display_name}")
+            };
+            let _ = msg.reply(ctx, response).await;
+        }
+        Some("unsubscribe") => {
+            alert_store.unsubscribe(msg.channel_id.0 as i64).await?;
+            let _ = msg.react(ctx, '👍').await;
+        }
+        _ => return invalid_command(ctx, msg).await,
+    }
+
     Ok(())
 }
 
-fn c_to_f(temp: f32) -> f32 {
-    (temp * (9. / 5.)) + 32.
+/// Periodically checks for new weather alerts and posts them to subscribed channels.
+pub(crate) async fn poll_weather_alerts<S: AlertSource>(
+    http: Arc<Http>,
+    scheduler: AlertScheduler<S>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        let new_alerts = match scheduler.poll().await {
+            Ok(new_alerts) => new_alerts,
+            Err(why) => {
+                warn!("failed to poll weather alerts: {why:?}");
+                continue;
+            }
+        };
+
+        for (subscription, alert) in new_alerts {
+            let result = ChannelId(subscription.channel as u64)
+                .send_message(&http, |m| {
+                    m.embed(|e| {
+                        embed_alert(e, &subscription, &alert);
+                        e
+                    });
+
+                    m
+                })
+                .await;
+
+            // Alerts which fail to send are retried on the next poll.
+            if let Err(why) = result {
+                warn!("failed to post weather alert: {why:?}");
+            } else if let Err(why) = scheduler.mark_posted(&subscription, &alert).await {
+                warn!("failed to mark weather alert as posted: {why:?}");
+            }
+        }
+    }
+}
+
+#[command]
+async fn weather(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if !ctx
+        .data
+        .read()
+        .await
+        .contains_key::<WeatherProviderContainer>()
+    {
+        return reply_not_configured(ctx, msg).await;
+    }
+
+    match args.current() {
+        Some("set") => {
+            args.advance();
+            return set_location(ctx, msg, args).await;
+        }
+        Some("units") => {
+            args.advance();
+            return set_units(ctx, msg, args).await;
+        }
+        Some("share") => {
+            args.advance();
+            return set_share_location(ctx, msg, args).await;
+        }
+        Some("alerts") => {
+            args.advance();
+            return alerts(ctx, msg, args).await;
+        }
+        _ => {}
+    }
+
+    let (place, hourly) = split_hourly(args.rest());
+    if hourly {
+        return hourly_weather(ctx, msg, place).await;
+    }
+
+    let Some(((display_name, lat, lon), units)) = resolve_location(ctx, msg, place).await? else {
+        return Ok(());
+    };
+
+    // Now look up the weather at this lat/lon.
+    let data = ctx.data.read().await;
+    let provider = data
+        .get::<WeatherProviderContainer>()
+        .expect("failed to obtain weather provider");
+
+    let current = match provider.current(&lat, &lon).await {
+        Ok(weather) => weather,
+        Err(why) => {
+            reply_with_error(ctx, msg, why).await;
+            return Ok(());
+        }
+    };
+
+    let _ = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                embed_weather(e, &display_name, &current, units);
+                e
+            });
+
+            m
+        })
+        .await;
+
+    Ok(())
+}
+
+async fn hourly_weather(ctx: &Context, msg: &Message, place: &str) -> CommandResult {
+    let Some(((display_name, lat, lon), units)) = resolve_location(ctx, msg, place).await? else {
+        return Ok(());
+    };
+
+    let data = ctx.data.read().await;
+    let provider = data
+        .get::<WeatherProviderContainer>()
+        .expect("failed to obtain weather provider");
+    let forecast = match provider.forecast(&lat, &lon).await {
+        Ok(forecast) => forecast,
+        Err(why) => {
+            reply_with_error(ctx, msg, why).await;
+            return Ok(());
+        }
+    };
+
+    let _ = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title(format!("Next {HOURLY_HOURS} hours in {display_name}"));
+
+                for (time, entry) in forecast.hourly(HOURLY_HOURS) {
+                    e.field(
+                        time.format("%H:%M"),
+                        format!(
+                            "{} {}\n:umbrella: {:.0}%",
+                            entry.emoji,
+                            format_temp(entry.temp, units),
+                            entry.pop * 100.
+                        ),
+                        true,
+                    );
+                }
+
+                e
+            });
+
+            m
+        })
+        .await;
+
+    Ok(())
+}
+
+#[command]
+async fn forecast(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if !ctx
+        .data
+        .read()
+        .await
+        .contains_key::<WeatherProviderContainer>()
+    {
+        return reply_not_configured(ctx, msg).await;
+    }
+
+    let Some(((display_name, lat, lon), units)) =
+        resolve_location(ctx, msg, args.rest().trim()).await?
+    else {
+        return Ok(());
+    };
+
+    let data = ctx.data.read().await;
+    let provider = data
+        .get::<WeatherProviderContainer>()
+        .expect("failed to obtain weather provider");
+    let forecast = match provider.forecast(&lat, &lon).await {
+        Ok(forecast) => forecast,
+        Err(why) => {
+            reply_with_error(ctx, msg, why).await;
+            return Ok(());
+        }
+    };
+
+    let _ = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title(format!("Forecast for {display_name}"));
+
+                for day in forecast.daily() {
+                    e.field(
+                        day.date.format("%a %e %b"),
+                        format!(
+                            "{} {}\n:umbrella: {:.0}%",
+                            day.emoji,
+                            format_temp_range(day.temp_min, day.temp_max, units),
+                            day.pop * 100.
+                        ),
+                        true,
+                    );
+                }
+
+                e
+            });
+
+            m
+        })
+        .await;
+
+    Ok(())
+}
+
+fn embed_air_quality(
+    e: &mut CreateEmbed,
+    display_name: &str,
+    air_quality: &AirQuality,
+    uv_index: Option<f32>,
+) {
+    let level = air_quality.level;
+    let pollutants = &air_quality.pollutants;
+
+    e.title(format!("Air quality in {display_name}"));
+    e.colour(level.colour());
+    e.description(format!(
+        "**{}** ({}/5)\n{}",
+        level.name(),
+        level.index(),
+        level.advice()
+    ));
+
+    e.field("PM2.5:", format!("{:.1} μg/m³", pollutants.pm2_5), true);
+    e.field("PM10:", format!("{:.1} μg/m³", pollutants.pm10), true);
+    e.field("O₃:", format!("{:.1} μg/m³", pollutants.o3), true);
+    e.field("NO₂:", format!("{:.1} μg/m³", pollutants.no2), true);
+    e.field("SO₂:", format!("{:.1} μg/m³", pollutants.so2), true);
+    e.field("CO:", format!("{:.1} μg/m³", pollutants.co), true);
+
+    // Neither OpenWeatherMap nor Open-Meteo's forecast API report pollen, so it isn't shown.
+
+    if let Some(uvi) = uv_index {
+        e.field(
+            "UV index:",
+            format!("{uvi:.1} ({})", uv_category(uvi)),
+            true,
+        );
+    }
+}
+
+#[command]
+async fn aqi(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if !ctx
+        .data
+        .read()
+        .await
+        .contains_key::<OpenWeatherMapClientContainer>()
+    {
+        let _ = msg
+            .reply(ctx, "Air quality requires an OpenWeatherMap API key.")
+            .await;
+        return Ok(());
+    }
+
+    let Some(((display_name, lat, lon), _)) =
+        resolve_location(ctx, msg, args.rest().trim()).await?
+    else {
+        return Ok(());
+    };
+
+    let data = ctx.data.read().await;
+    let owm_client = data
+        .get::<OpenWeatherMapClientContainer>()
+        .expect("failed to open OpenWeatherMap client");
+
+    let air_quality = match owm_client.air_pollution(&lat, &lon).await {
+        Ok(air_quality) => air_quality,
+        Err(why) => {
+            reply_with_error(ctx, msg, why).await;
+            return Ok(());
+        }
+    };
+    let uv_index = owm_client.uv_index(&lat, &lon).await.unwrap_or_else(|why| {
+        warn!("failed to get UV index: {why:?}");
+        None
+    });
+
+    let _ = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                embed_air_quality(e, &display_name, &air_quality, uv_index);
+                e
+            });
+
+            m
+        })
+        .await;
+
+    Ok(())
+}
+
+#[command]
+#[owners_only]
+async fn geocache(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.single::<String>().ok().as_deref() != Some("purge") {
+        return invalid_command(ctx, msg).await;
+    }
+
+    let data = ctx.data.read().await;
+    let nominatim_client = data
+        .get::<NominatimClientContainer>()
+        .expect("failed to obtain nominatim client");
+
+    let query = Some(args.rest().trim()).filter(|query| !query.is_empty());
+    let purged = nominatim_client.purge_cache(query).await?;
+
+    let _ = msg
+        .reply(ctx, format!("Purged {purged} cached location(s)"))
+        .await;
+    Ok(())
 }
@@ -1,4 +1,13 @@
-use crate::models::weather::{NominatimClient, OpenWeatherMapClient};
+use crate::models::achievements::AchievementStore;
+use crate::models::calc::Calculator;
+use crate::models::guild_settings::GuildSettings;
+use crate::models::loot::LootGame;
+use crate::models::preferences::PreferenceStore;
+use crate::models::sandboxes::{ReplyCache, SandboxRunner};
+use crate::models::snippets::SnippetStore;
+use crate::models::weather::alerts::AlertStore;
+use crate::models::weather::{NominatimClient, OpenWeatherMapClient, WeatherProvider};
+use crate::models::zoo::AnimalPostStore;
 use crate::{AnimalGateway, CardStore, CountdownStore, RockCounter};
 use serenity::client::bridge::gateway::ShardManager;
 use serenity::model::application::CurrentApplicationInfo;
@@ -30,6 +39,18 @@ impl TypeMapKey for RockCounterContainer {
     type Value = RockCounter<'static>;
 }
 
+pub struct AchievementStoreContainer;
+
+impl TypeMapKey for AchievementStoreContainer {
+    type Value = AchievementStore<'static>;
+}
+
+pub struct LootGameContainer;
+
+impl TypeMapKey for LootGameContainer {
+    type Value = LootGame<'static>;
+}
+
 pub struct CountdownStoreContainer;
 
 impl TypeMapKey for CountdownStoreContainer {
@@ -39,7 +60,13 @@ impl TypeMapKey for CountdownStoreContainer {
 pub struct AnimalGatewayContainer;
 
 impl TypeMapKey for AnimalGatewayContainer {
-    type Value = AnimalGateway;
+    type Value = Arc<AnimalGateway>;
+}
+
+pub struct AnimalPostStoreContainer;
+
+impl TypeMapKey for AnimalPostStoreContainer {
+    type Value = AnimalPostStore<'static>;
 }
 
 pub struct NominatimClientContainer;
@@ -53,3 +80,51 @@ pub struct OpenWeatherMapClientContainer;
 impl TypeMapKey for OpenWeatherMapClientContainer {
     type Value = OpenWeatherMapClient;
 }
+
+pub struct WeatherProviderContainer;
+
+impl TypeMapKey for WeatherProviderContainer {
+    type Value = Box<dyn WeatherProvider>;
+}
+
+pub struct SandboxRunnerContainer;
+
+impl TypeMapKey for SandboxRunnerContainer {
+    type Value = SandboxRunner;
+}
+
+pub struct SandboxReplyCacheContainer;
+
+impl TypeMapKey for SandboxReplyCacheContainer {
+    type Value = ReplyCache;
+}
+
+pub struct SnippetStoreContainer;
+
+impl TypeMapKey for SnippetStoreContainer {
+    type Value = SnippetStore<'static>;
+}
+
+pub struct PreferenceStoreContainer;
+
+impl TypeMapKey for PreferenceStoreContainer {
+    type Value = PreferenceStore<'static>;
+}
+
+pub struct AlertStoreContainer;
+
+impl TypeMapKey for AlertStoreContainer {
+    type Value = AlertStore<'static>;
+}
+
+pub struct CalculatorContainer;
+
+impl TypeMapKey for CalculatorContainer {
+    type Value = Calculator;
+}
+
+pub struct GuildSettingsContainer;
+
+impl TypeMapKey for GuildSettingsContainer {
+    type Value = Arc<GuildSettings<'static>>;
+}
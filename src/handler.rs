@@ -0,0 +1,49 @@
+use crate::commands::animals::record_reaction;
+use crate::commands::sandboxes::{delete_reply, rerun_edited};
+use serenity::async_trait;
+use serenity::client::{Context, EventHandler};
+use serenity::model::channel::{Message, Reaction};
+use serenity::model::event::MessageUpdateEvent;
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use tracing::warn;
+
+pub struct Handler;
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        _channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        if let Err(why) = delete_reply(&ctx, deleted_message_id).await {
+            warn!("failed to delete sandbox reply: {why:?}");
+        }
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        _new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        if let Err(why) = rerun_edited(&ctx, &event).await {
+            warn!("failed to re-run edited sandbox: {why:?}");
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        if let Err(why) = record_reaction(&ctx, &reaction, true).await {
+            warn!("failed to record animal reaction: {why:?}");
+        }
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        if let Err(why) = record_reaction(&ctx, &reaction, false).await {
+            warn!("failed to record animal reaction: {why:?}");
+        }
+    }
+}
@@ -1,19 +1,33 @@
 use crate::commands::{
-    animals::*, countdown::*, dig::*, help::*, mtg::*, probability::*, quit::*, sandboxes::*,
-    weather::*,
+    animals::*, calc::*, config::*, countdown::*, dice::*, dig::*, help::*, mtg::*, probability::*,
+    quit::*, sandboxes::*, snippets::*, statistics::*, weather::*,
 };
 use crate::containers::{
-    AnimalGatewayContainer, AppInfoContainer, CardStoreContainer, CountdownStoreContainer,
-    NominatimClientContainer, OpenWeatherMapClientContainer, RockCounterContainer,
-    ShardManagerContainer,
+    AchievementStoreContainer, AlertStoreContainer, AnimalGatewayContainer,
+    AnimalPostStoreContainer, AppInfoContainer, CalculatorContainer, CardStoreContainer,
+    CountdownStoreContainer, GuildSettingsContainer, LootGameContainer, NominatimClientContainer,
+    OpenWeatherMapClientContainer, PreferenceStoreContainer, RockCounterContainer,
+    SandboxReplyCacheContainer, SandboxRunnerContainer, ShardManagerContainer,
+    SnippetStoreContainer, WeatherProviderContainer,
 };
+use crate::handler::Handler;
+use crate::models::achievements::AchievementStore;
+use crate::models::calc::Calculator;
 use crate::models::cards::CardStore;
 use crate::models::countdowns::CountdownStore;
+use crate::models::guild_settings::GuildSettings;
+use crate::models::loot::{LootGame, LootTable};
+use crate::models::preferences::PreferenceStore;
 use crate::models::rocks::RockCounter;
-use crate::models::zoo::AnimalGateway;
+use crate::models::sandboxes::{ReplyCache, SandboxConfig, SandboxRunner};
+use crate::models::snippets::SnippetStore;
+use crate::models::zoo::{AnimalGateway, AnimalPostStore};
 
-use crate::models::weather::{NominatimClient, OpenWeatherMapClient};
-use anyhow::Result;
+use crate::models::weather::alerts::{AlertScheduler, AlertStore};
+use crate::models::weather::{
+    NominatimClient, OpenMeteoClient, OpenWeatherMapClient, WeatherProvider,
+};
+use anyhow::{Context, Result};
 use serde::Deserialize;
 use serenity::framework::standard::macros::group;
 use serenity::framework::StandardFramework;
@@ -25,15 +39,50 @@ use serenity::Client;
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::{Pool, Sqlite};
 use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
 
 mod client;
 mod commands;
 mod containers;
+mod handler;
 mod models;
 
 #[group]
 #[commands(
-    countdown, dig, dog, cat, normalcdf, py, py_raw, rust, rust_raw, quit, weather
+    achievements,
+    aqi,
+    calc,
+    config,
+    countdown,
+    dig,
+    dog,
+    cat,
+    duck,
+    forecast,
+    fox,
+    geocache,
+    inventory,
+    normalcdf,
+    py,
+    py_raw,
+    rust,
+    rust_raw,
+    quit,
+    roll,
+    snippet,
+    stats,
+    weather,
+    zoo,
+    binomcdf,
+    binompdf,
+    chisqcdf,
+    expcdf,
+    invnorm,
+    poissoncdf,
+    poissonpdf,
+    tcdf
 )]
 struct General;
 
@@ -46,7 +95,105 @@ struct Config {
     database_url: String,
     discord_token: String,
     discord_application_id: u64,
-    openweather_api_key: String,
+    openweather_api_key: Option<String>,
+    /// Whether the OpenWeatherMap key is subscribed to One Call 3.0, which provides weather
+    /// alerts and the UV index.  The subscription is paid for separately, so this defaults
+    /// to false.
+    openweather_one_call: Option<bool>,
+    /// Either `openweathermap` or `open-meteo`; defaults to OpenWeatherMap if a key is set.
+    weather_provider: Option<String>,
+    sandbox_max_concurrent: Option<usize>,
+    sandbox_max_queued: Option<usize>,
+    sandbox_runs_per_minute: Option<u32>,
+    sandbox_memory_mb: Option<u64>,
+    sandbox_cpus: Option<f64>,
+    sandbox_timeout_secs: Option<u64>,
+    sandbox_compile_timeout_secs: Option<u64>,
+    sandbox_network: Option<bool>,
+    /// How many sandbox replies are remembered, so they can be updated when edited.
+    sandbox_reply_cache_size: Option<usize>,
+    sandbox_reply_cache_ttl_secs: Option<u64>,
+    weather_alert_interval_secs: Option<u64>,
+    /// Path to a JSON loot table to use instead of the built-in one.
+    loot_table_path: Option<String>,
+    /// Seeds the loot RNG, making digs reproducible.
+    loot_seed: Option<u64>,
+}
+
+impl Config {
+    /// Builds the sandbox configuration, falling back to the defaults for anything unset.
+    fn sandbox_config(&self) -> SandboxConfig {
+        let mut sandbox_config = SandboxConfig::default();
+
+        if let Some(max_concurrent) = self.sandbox_max_concurrent {
+            sandbox_config.max_concurrent = max_concurrent;
+        }
+        if let Some(max_queued) = self.sandbox_max_queued {
+            sandbox_config.max_queued = max_queued;
+        }
+        if let Some(runs_per_minute) = self.sandbox_runs_per_minute {
+            sandbox_config.runs_per_minute = runs_per_minute;
+        }
+        if let Some(memory_mb) = self.sandbox_memory_mb {
+            sandbox_config.limits.memory_mb = memory_mb;
+        }
+        if let Some(cpus) = self.sandbox_cpus {
+            sandbox_config.limits.cpus = cpus;
+        }
+        if let Some(timeout_secs) = self.sandbox_timeout_secs {
+            sandbox_config.limits.timeout = Duration::from_secs(timeout_secs);
+        }
+        if let Some(compile_timeout_secs) = self.sandbox_compile_timeout_secs {
+            sandbox_config.limits.compile_timeout = Duration::from_secs(compile_timeout_secs);
+        }
+        if let Some(network) = self.sandbox_network {
+            sandbox_config.limits.network = network;
+        }
+
+        sandbox_config
+    }
+
+    /// Builds the cache of sandbox replies, which are re-run when their message is edited.
+    fn reply_cache(&self) -> ReplyCache {
+        ReplyCache::new(
+            self.sandbox_reply_cache_size.unwrap_or(500),
+            Duration::from_secs(self.sandbox_reply_cache_ttl_secs.unwrap_or(60 * 60)),
+        )
+    }
+
+    fn loot_table(&self) -> Result<LootTable> {
+        match &self.loot_table_path {
+            Some(path) => {
+                let json = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read loot table {path}"))?;
+                LootTable::from_json(&json)
+            }
+            None => Ok(LootTable::builtin()),
+        }
+    }
+
+    fn openweathermap_client(&self, api_key: &str) -> OpenWeatherMapClient {
+        OpenWeatherMapClient::new(api_key, self.openweather_one_call.unwrap_or(false))
+    }
+
+    /// Builds the configured weather provider, if there is one.
+    fn weather_provider(&self) -> Option<Box<dyn WeatherProvider>> {
+        match (self.weather_provider.as_deref(), &self.openweather_api_key) {
+            (Some("open-meteo"), _) => Some(Box::new(OpenMeteoClient::new())),
+            (Some("openweathermap") | None, Some(api_key)) => {
+                Some(Box::new(self.openweathermap_client(api_key)))
+            }
+            (Some("openweathermap"), None) => {
+                warn!("openweathermap requires OPENWEATHER_API_KEY, weather is disabled");
+                None
+            }
+            (Some(provider), _) => {
+                warn!("unknown weather provider {provider}, weather is disabled");
+                None
+            }
+            (None, None) => None,
+        }
+    }
 }
 
 fn setup_app() -> Result<()> {
@@ -78,11 +225,20 @@ async fn get_bot_info(token: &str) -> (HashSet<UserId>, CurrentApplicationInfo)
     }
 }
 
-async fn build_client(config: &Config, pool: &'static Pool<Sqlite>) -> Client {
+async fn build_client(
+    config: &Config,
+    pool: &'static Pool<Sqlite>,
+    guild_settings: Arc<GuildSettings<'static>>,
+    animal_gateway: Arc<AnimalGateway>,
+) -> Client {
     let (owners, current_app_info) = get_bot_info(&config.discord_token).await;
 
     let framework = StandardFramework::new()
-        .configure(|c| c.owners(owners).prefix("~"))
+        .configure(|c| {
+            // Serenity answers to `~` on top of any dynamic prefix unless the static one is cleared.
+            c.owners(owners).prefix("").dynamic_prefix(dynamic_prefix)
+        })
+        .before(check_enabled)
         .group(&GENERAL_GROUP)
         .group(&MTG_GROUP)
         .help(&MY_HELP);
@@ -91,6 +247,7 @@ async fn build_client(config: &Config, pool: &'static Pool<Sqlite>) -> Client {
         &config.discord_token,
         GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT,
     )
+    .event_handler(Handler)
     .framework(framework)
     .application_id(config.discord_application_id)
     .await
@@ -102,14 +259,32 @@ async fn build_client(config: &Config, pool: &'static Pool<Sqlite>) -> Client {
         let mut data = client.data.write().await;
         data.insert::<AppInfoContainer>(current_app_info);
         data.insert::<CardStoreContainer>(CardStore::new(pool));
+        data.insert::<CalculatorContainer>(Calculator::default());
         data.insert::<RockCounterContainer>(RockCounter::new(pool));
+        data.insert::<AchievementStoreContainer>(AchievementStore::new(pool));
+        data.insert::<LootGameContainer>(LootGame::new(
+            pool,
+            config.loot_table().expect("failed to load loot table"),
+            config.loot_seed,
+        ));
         data.insert::<CountdownStoreContainer>(CountdownStore::new(pool));
-        data.insert::<AnimalGatewayContainer>(AnimalGateway::new());
+        data.insert::<GuildSettingsContainer>(guild_settings);
+        data.insert::<SnippetStoreContainer>(SnippetStore::new(pool));
+        data.insert::<PreferenceStoreContainer>(PreferenceStore::new(pool));
+        data.insert::<AlertStoreContainer>(AlertStore::new(pool));
+        data.insert::<AnimalGatewayContainer>(animal_gateway);
+        data.insert::<AnimalPostStoreContainer>(AnimalPostStore::new(pool));
         data.insert::<ShardManagerContainer>(client.shard_manager.clone());
-        data.insert::<NominatimClientContainer>(NominatimClient::new());
-        data.insert::<OpenWeatherMapClientContainer>(OpenWeatherMapClient::new(
-            &config.openweather_api_key,
-        ));
+        data.insert::<SandboxRunnerContainer>(SandboxRunner::new(config.sandbox_config()));
+        data.insert::<SandboxReplyCacheContainer>(config.reply_cache());
+        data.insert::<NominatimClientContainer>(NominatimClient::new(pool));
+        if let Some(provider) = config.weather_provider() {
+            data.insert::<WeatherProviderContainer>(provider);
+        }
+        // Alerts are only available from OpenWeatherMap, whichever provider is in use.
+        if let Some(api_key) = &config.openweather_api_key {
+            data.insert::<OpenWeatherMapClientContainer>(config.openweathermap_client(api_key));
+        }
     }
 
     client
@@ -121,7 +296,49 @@ async fn main() -> Result<()> {
 
     let config: Config = envy::from_env()?;
     let pool = setup_db_pool(&config).await?;
-    let mut client = build_client(&config, pool).await;
+    let guild_settings = Arc::new(GuildSettings::new(pool));
+    let animal_gateway = Arc::new(AnimalGateway::new(pool));
+    let mut client = build_client(
+        &config,
+        pool,
+        guild_settings.clone(),
+        animal_gateway.clone(),
+    )
+    .await;
+
+    // Announce countdowns as they end
+    tokio::spawn(announce_countdowns(
+        client.cache_and_http.http.clone(),
+        CountdownStore::new(pool),
+        guild_settings,
+        Duration::from_secs(60),
+    ));
+
+    // Periodically post severe weather alerts to subscribed channels
+    if let Some(api_key) = config
+        .openweather_api_key
+        .as_ref()
+        .filter(|_| config.openweather_one_call.unwrap_or(false))
+    {
+        let alert_scheduler =
+            AlertScheduler::new(AlertStore::new(pool), config.openweathermap_client(api_key));
+        tokio::spawn(poll_weather_alerts(
+            client.cache_and_http.http.clone(),
+            alert_scheduler,
+            Duration::from_secs(config.weather_alert_interval_secs.unwrap_or(15 * 60)),
+        ));
+    }
+
+    // Keep the animal image pools topped up so that commands can answer instantly
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(10 * 60));
+        loop {
+            ticker.tick().await;
+            if let Err(why) = animal_gateway.refill().await {
+                warn!("failed to refill animal image pools: {why:?}");
+            }
+        }
+    });
 
     // Set ctrl+c handler so we can shut down the running bot
     let shard_manager = client.shard_manager.clone();
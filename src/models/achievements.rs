@@ -0,0 +1,282 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+
+/// What an achievement's threshold is compared against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum Kind {
+    Digs,
+    DailyStreak,
+}
+
+#[derive(Debug, Clone)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub kind: Kind,
+    pub threshold: i64,
+}
+
+impl Achievement {
+    /// Returns whether a user with the given dig count and daily streak has earned this.
+    pub fn is_earned(&self, digs: i64, daily_streak: i64) -> bool {
+        match self.kind {
+            Kind::Digs => digs >= self.threshold,
+            Kind::DailyStreak => daily_streak >= self.threshold,
+        }
+    }
+}
+
+/// A role granted in a guild for earning an achievement.
+#[derive(Debug, Clone)]
+pub struct AchievementRole {
+    /// The name of the achievement.
+    pub name: String,
+    pub role_id: i64,
+}
+
+pub struct AchievementStore<'pool> {
+    pool: &'pool Pool<Sqlite>,
+}
+
+impl<'pool> AchievementStore<'pool> {
+    pub fn new(pool: &'pool Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn definitions(&self) -> Result<Vec<Achievement>> {
+        sqlx::query_as!(
+            Achievement,
+            r#"
+        SELECT id, name, description, kind as "kind: Kind", threshold
+        FROM achievements
+        ORDER BY threshold
+            "#
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| "failed to get achievements")
+    }
+
+    /// Awards the given user every achievement they've now earned, returning the ones they
+    /// didn't already have.
+    pub async fn award(
+        &self,
+        user_id: i64,
+        digs: i64,
+        daily_streak: i64,
+    ) -> Result<Vec<Achievement>> {
+        let earned_at = Utc::now().timestamp();
+        let mut awarded = Vec::new();
+
+        for achievement in self.definitions().await? {
+            if !achievement.is_earned(digs, daily_streak) {
+                continue;
+            }
+
+            let is_new = sqlx::query!(
+                "
+            INSERT OR IGNORE INTO earned_achievements (user_id, achievement_id, earned_at)
+            VALUES (?, ?, ?)
+                ",
+                user_id,
+                achievement.id,
+                earned_at
+            )
+            .execute(self.pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .with_context(|| format!("failed to award {} to user {user_id}", achievement.id))?;
+
+            if is_new {
+                awarded.push(achievement);
+            }
+        }
+
+        Ok(awarded)
+    }
+
+    /// Returns the achievements the given user has earned, in the order they earned them.
+    pub async fn earned(&self, user_id: i64) -> Result<Vec<Achievement>> {
+        sqlx::query_as!(
+            Achievement,
+            r#"
+        SELECT a.id, a.name, a.description, a.kind as "kind: Kind", a.threshold
+        FROM achievements a
+        JOIN earned_achievements e ON e.achievement_id = a.id
+        WHERE e.user_id = ?
+        ORDER BY e.earned_at
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| format!("failed to get achievements for user {user_id}"))
+    }
+
+    /// Returns the roles the given user should have in a guild for the achievements they've
+    /// earned, wherever they earned them, which haven't been granted to them yet.
+    pub async fn pending_roles(&self, guild: i64, user_id: i64) -> Result<Vec<AchievementRole>> {
+        sqlx::query_as!(
+            AchievementRole,
+            "
+        SELECT a.name, r.role_id
+        FROM achievement_roles r
+        JOIN achievements a ON a.id = r.achievement_id
+        JOIN earned_achievements e ON e.achievement_id = r.achievement_id
+        WHERE r.guild = ? AND e.user_id = ? AND NOT EXISTS (
+            SELECT 1 FROM achievement_role_grants g
+            WHERE g.guild = r.guild AND g.user_id = e.user_id AND g.role_id = r.role_id
+        )
+            ",
+            guild,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| {
+            format!("failed to get achievement roles for user {user_id} in guild {guild}")
+        })
+    }
+
+    /// Records that granting a role to the given user has been attempted, whether or not it
+    /// succeeded, so it isn't attempted again on every dig.
+    pub async fn record_grant(&self, guild: i64, user_id: i64, role_id: i64) -> Result<()> {
+        sqlx::query!(
+            "
+        INSERT OR IGNORE INTO achievement_role_grants (guild, user_id, role_id)
+        VALUES (?, ?, ?)
+            ",
+            guild,
+            user_id,
+            role_id
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to record role {role_id} for user {user_id}"))
+    }
+
+    /// Sets (or with `None`, clears) the role granted in the given guild for earning an
+    /// achievement.
+    pub async fn set_role(
+        &self,
+        guild: i64,
+        achievement_id: &str,
+        role_id: Option<i64>,
+    ) -> Result<()> {
+        let result = match role_id {
+            Some(role_id) => {
+                sqlx::query!(
+                    "
+                INSERT OR REPLACE INTO achievement_roles (guild, achievement_id, role_id)
+                VALUES (?, ?, ?)
+                    ",
+                    guild,
+                    achievement_id,
+                    role_id
+                )
+                .execute(self.pool)
+                .await
+            }
+            None => {
+                sqlx::query!(
+                    "DELETE FROM achievement_roles WHERE guild = ? AND achievement_id = ?",
+                    guild,
+                    achievement_id
+                )
+                .execute(self.pool)
+                .await
+            }
+        };
+
+        result
+            .map(|_| ())
+            .with_context(|| format!("failed to set role for {achievement_id} in guild {guild}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_pool;
+
+    fn ids(achievements: &[Achievement]) -> Vec<&str> {
+        achievements.iter().map(|a| a.id.as_str()).collect()
+    }
+
+    fn role_ids(roles: &[AchievementRole]) -> Vec<i64> {
+        roles.iter().map(|role| role.role_id).collect()
+    }
+
+    #[tokio::test]
+    async fn reads_definitions() {
+        let pool = test_pool().await;
+        let definitions = AchievementStore::new(&pool).definitions().await.unwrap();
+
+        let streak = definitions.iter().find(|a| a.id == "streak_7").unwrap();
+        assert_eq!(streak.kind, Kind::DailyStreak);
+        assert!(streak.is_earned(0, 7));
+        assert!(!streak.is_earned(100, 6));
+
+        let digs = definitions.iter().find(|a| a.id == "digs_100").unwrap();
+        assert_eq!(digs.kind, Kind::Digs);
+        assert!(digs.is_earned(100, 0));
+        assert!(!digs.is_earned(99, 7));
+    }
+
+    #[tokio::test]
+    async fn awards_each_achievement_once() {
+        let pool = test_pool().await;
+        let store = AchievementStore::new(&pool);
+
+        assert!(store.award(1, 99, 0).await.unwrap().is_empty());
+        assert_eq!(ids(&store.award(1, 150, 0).await.unwrap()), ["digs_100"]);
+        assert!(store.award(1, 200, 0).await.unwrap().is_empty());
+        assert_eq!(
+            ids(&store.award(1, 1000, 7).await.unwrap()),
+            ["streak_7", "digs_1000"]
+        );
+
+        assert_eq!(store.earned(1).await.unwrap().len(), 3);
+        assert!(store.earned(2).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn grants_roles_once_per_configuration() {
+        let pool = test_pool().await;
+        let store = AchievementStore::new(&pool);
+        let (guild, user) = (10, 1);
+
+        store.set_role(guild, "digs_100", Some(100)).await.unwrap();
+        assert!(store.pending_roles(guild, user).await.unwrap().is_empty());
+
+        store.award(user, 100, 0).await.unwrap();
+        assert_eq!(
+            role_ids(&store.pending_roles(guild, user).await.unwrap()),
+            [100]
+        );
+        assert!(store
+            .pending_roles(guild + 1, user)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // A failed grant is recorded too, so it isn't retried on every dig.
+        store.record_grant(guild, user, 100).await.unwrap();
+        store.record_grant(guild, user, 100).await.unwrap();
+        assert!(store.pending_roles(guild, user).await.unwrap().is_empty());
+
+        // Configuring a different role makes it pending again.
+        store.set_role(guild, "digs_100", Some(101)).await.unwrap();
+        assert_eq!(
+            role_ids(&store.pending_roles(guild, user).await.unwrap()),
+            [101]
+        );
+
+        store.set_role(guild, "digs_100", None).await.unwrap();
+        assert!(store.pending_roles(guild, user).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,397 @@
+use anyhow::{bail, Result};
+use parser::{BinaryOp, Expr};
+use std::collections::HashMap;
+use std::f64::consts;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use units::Unit;
+
+mod parser;
+pub mod units;
+
+/// The longest input the calculator will look at.
+const MAX_INPUT_LENGTH: usize = 256;
+/// How deeply brackets, function calls and signs can nest.  Chains of operators like
+/// `1+1+…+1` aren't counted, since the input length already limits those.
+const MAX_DEPTH: usize = 64;
+/// How many variables each user can have.
+const MAX_VARIABLES: usize = 32;
+/// How long a user's variables are kept after they last used the calculator.
+const SESSION_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+/// The variable holding the result of the last calculation.
+const ANSWER: &str = "ans";
+
+const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", consts::PI),
+    ("π", consts::PI),
+    ("tau", consts::TAU),
+    ("τ", consts::TAU),
+    ("e", consts::E),
+    ("phi", 1.618033988749895),
+    ("φ", 1.618033988749895),
+];
+
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh", "tanh", "sqrt", "cbrt", "exp",
+    "ln", "log", "abs", "floor", "ceil", "round", "min", "max",
+];
+
+/// A number, possibly with a unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Value {
+    pub number: f64,
+    pub unit: Option<&'static Unit>,
+}
+
+impl Value {
+    fn plain(number: f64) -> Self {
+        Self { number, unit: None }
+    }
+
+    /// Returns the number, provided this value doesn't have a unit.
+    fn unitless(&self, context: &str) -> Result<f64> {
+        match self.unit {
+            None => Ok(self.number),
+            Some(unit) => bail!("{context} needs a plain number, not {}", unit.name()),
+        }
+    }
+
+    fn convert(&self, to: &'static Unit) -> Result<Value> {
+        match self.unit {
+            Some(unit) if unit.dimension == to.dimension => Ok(Value {
+                number: unit.convert(self.number, to),
+                unit: Some(to),
+            }),
+            Some(unit) => bail!("can't convert {} to {}", unit.name(), to.name()),
+            None => bail!("{} has no unit to convert from", format_number(self.number)),
+        }
+    }
+}
+
+/// Formats a number without trailing zeros, switching to scientific notation for very large
+/// and very small numbers.
+fn format_number(number: f64) -> String {
+    let magnitude = number.abs();
+    if magnitude != 0. && !(1e-6..1e15).contains(&magnitude) {
+        return format!("{number:.6e}");
+    }
+    let formatted = format!("{number:.10}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_number(self.number))?;
+        match self.unit {
+            Some(unit) => write!(f, " {}", unit.name()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn call(name: &str, args: &[Value]) -> Result<f64> {
+    let numbers = args
+        .iter()
+        .map(|arg| arg.unitless(name))
+        .collect::<Result<Vec<_>>>()?;
+
+    let unary = |f: fn(f64) -> f64| match numbers.as_slice() {
+        [x] => Ok(f(*x)),
+        _ => bail!("{name} takes one argument"),
+    };
+    match name {
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "asin" => unary(f64::asin),
+        "acos" => unary(f64::acos),
+        "atan" => unary(f64::atan),
+        "sinh" => unary(f64::sinh),
+        "cosh" => unary(f64::cosh),
+        "tanh" => unary(f64::tanh),
+        "sqrt" => unary(f64::sqrt),
+        "cbrt" => unary(f64::cbrt),
+        "exp" => unary(f64::exp),
+        "ln" => unary(f64::ln),
+        "abs" => unary(f64::abs),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => unary(f64::round),
+        "log" => match numbers.as_slice() {
+            [x] => Ok(x.log10()),
+            [x, base] => Ok(x.log(*base)),
+            _ => bail!("log takes a number and optionally a base"),
+        },
+        "min" | "max" if numbers.is_empty() => bail!("{name} needs at least one argument"),
+        "min" => Ok(numbers.into_iter().fold(f64::INFINITY, f64::min)),
+        "max" => Ok(numbers.into_iter().fold(f64::NEG_INFINITY, f64::max)),
+        _ => bail!("there's no function called {name}"),
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Result<Value> {
+    let value = match (op, lhs.unit, rhs.unit) {
+        (BinaryOp::Add | BinaryOp::Subtract, Some(unit), Some(_)) => {
+            let rhs = rhs.convert(unit)?.number;
+            let number = if op == BinaryOp::Add {
+                lhs.number + rhs
+            } else {
+                lhs.number - rhs
+            };
+            Value {
+                number,
+                unit: Some(unit),
+            }
+        }
+        (BinaryOp::Add | BinaryOp::Subtract, Some(_), None)
+        | (BinaryOp::Add | BinaryOp::Subtract, None, Some(_)) => {
+            bail!("can't add or subtract {lhs} and {rhs}")
+        }
+        (BinaryOp::Multiply, Some(_), Some(_)) | (BinaryOp::Divide, _, Some(_)) => {
+            bail!("can't multiply or divide units together")
+        }
+        (BinaryOp::Multiply, None, unit) => Value {
+            number: lhs.number * rhs.number,
+            unit,
+        },
+        (BinaryOp::Multiply, unit, None) => Value {
+            number: lhs.number * rhs.number,
+            unit,
+        },
+        (BinaryOp::Divide, unit, None) => Value {
+            number: lhs.number / rhs.number,
+            unit,
+        },
+        (BinaryOp::Remainder, unit, None) => Value {
+            number: lhs.number % rhs.number,
+            unit,
+        },
+        (BinaryOp::Remainder, _, Some(_)) => bail!("can't take the remainder by {rhs}"),
+        (BinaryOp::Power, _, _) => {
+            Value::plain(lhs.unitless("^")?.powf(rhs.unitless("an exponent")?))
+        }
+        (BinaryOp::Add, None, None) => Value::plain(lhs.number + rhs.number),
+        (BinaryOp::Subtract, None, None) => Value::plain(lhs.number - rhs.number),
+    };
+
+    Ok(value)
+}
+
+/// Evaluates a parsed expression.  The parser has already limited how deeply it nests.
+fn evaluate(expr: &Expr, variables: &HashMap<String, Value>) -> Result<Value> {
+    let evaluate = |expr: &Expr| evaluate(expr, variables);
+
+    let value = match expr {
+        Expr::Number(number) => Value::plain(*number),
+        Expr::Variable(name) => match CONSTANTS.iter().find(|(constant, _)| constant == name) {
+            Some((_, number)) => Value::plain(*number),
+            None => match variables.get(name) {
+                Some(value) => *value,
+                None => bail!("{name} isn't defined"),
+            },
+        },
+        Expr::Negate(expr) => {
+            let value = evaluate(expr)?;
+            Value {
+                number: -value.number,
+                unit: value.unit,
+            }
+        }
+        Expr::Binary(op, lhs, rhs) => binary(*op, evaluate(lhs)?, evaluate(rhs)?)?,
+        Expr::Call(name, args) => {
+            let args = args.iter().map(evaluate).collect::<Result<Vec<_>>>()?;
+            Value::plain(call(name, &args)?)
+        }
+        Expr::WithUnit(expr, unit) => {
+            let number = evaluate(expr)?.unitless(unit.name())?;
+            Value {
+                number,
+                unit: Some(unit),
+            }
+        }
+    };
+
+    if !value.number.is_finite() {
+        bail!("the result isn't a finite number");
+    }
+    Ok(value)
+}
+
+/// The result of a line of input to the calculator.
+pub struct Evaluation {
+    /// The variable the result was assigned to, if any.
+    pub name: Option<String>,
+    pub value: Value,
+}
+
+struct Session {
+    variables: HashMap<String, Value>,
+    last_used: Instant,
+}
+
+/// Evaluates arithmetic for users, remembering each user's variables for a while.
+#[derive(Default)]
+pub struct Calculator {
+    sessions: Mutex<HashMap<u64, Session>>,
+}
+
+impl Calculator {
+    /// Evaluates `[name =] expression [in unit]` for the given user.
+    pub fn evaluate(&self, user_id: u64, input: &str) -> Result<Evaluation> {
+        if input.len() > MAX_INPUT_LENGTH {
+            bail!("expressions can be at most {MAX_INPUT_LENGTH} characters long");
+        }
+        let statement = parser::parse(input)?;
+
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| now - session.last_used < SESSION_TIMEOUT);
+        let session = sessions.entry(user_id).or_insert_with(|| Session {
+            variables: HashMap::new(),
+            last_used: now,
+        });
+        session.last_used = now;
+
+        let mut value = evaluate(&statement.expr, &session.variables)?;
+        if let Some(unit) = statement.convert_to {
+            value = value.convert(unit)?;
+        }
+
+        if let Some(name) = &statement.assign_to {
+            if name == ANSWER
+                || CONSTANTS.iter().any(|(constant, _)| constant == name)
+                || FUNCTIONS.contains(&name.as_str())
+            {
+                bail!("{name} can't be assigned to");
+            }
+            if !session.variables.contains_key(name) && session.variables.len() > MAX_VARIABLES {
+                bail!("you can have at most {MAX_VARIABLES} variables");
+            }
+            session.variables.insert(name.clone(), value);
+        }
+        session.variables.insert(ANSWER.to_string(), value);
+
+        Ok(Evaluation {
+            name: statement.assign_to,
+            value,
+        })
+    }
+
+    /// Returns the given user's variables, sorted by name.
+    pub fn variables(&self, user_id: u64) -> Vec<(String, Value)> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut variables = sessions
+            .get(&user_id)
+            .map(|session| {
+                session
+                    .variables
+                    .iter()
+                    .map(|(name, value)| (name.clone(), *value))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+        variables
+    }
+
+    pub fn clear(&self, user_id: u64) {
+        self.sessions.lock().unwrap().remove(&user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calculate(input: &str) -> Result<String> {
+        Calculator::default()
+            .evaluate(1, input)
+            .map(|evaluation| evaluation.value.to_string())
+    }
+
+    #[test]
+    fn follows_precedence() {
+        assert_eq!(calculate("1 + 2 * 3").unwrap(), "7");
+        assert_eq!(calculate("(1 + 2) * 3").unwrap(), "9");
+        assert_eq!(calculate("2 ^ 3 ^ 2").unwrap(), "512");
+        assert_eq!(calculate("-2 ^ 2").unwrap(), "-4");
+        assert_eq!(calculate("2 ^ -1").unwrap(), "0.5");
+        assert_eq!(calculate("7 % 4 - 10 / 4").unwrap(), "0.5");
+        assert_eq!(calculate("0.1 + 0.2").unwrap(), "0.3");
+        assert_eq!(calculate("2 ^ 60").unwrap(), "1.152922e18");
+    }
+
+    #[test]
+    fn calls_functions_and_constants() {
+        assert_eq!(calculate("sqrt(16) + max(1, 5, 3)").unwrap(), "9");
+        assert_eq!(calculate("log(100) + log(8, 2)").unwrap(), "5");
+        assert_eq!(calculate("round(pi * 100)").unwrap(), "314");
+        assert!(calculate("sqrt(1, 2)").is_err());
+        assert!(calculate("nope(1)").is_err());
+        assert!(calculate("1 / 0").is_err());
+    }
+
+    #[test]
+    fn limits_nesting_but_not_chains() {
+        let chain = vec!["1"; 128].join("+");
+        assert!(chain.len() <= MAX_INPUT_LENGTH);
+        assert_eq!(calculate(&chain).unwrap(), "128");
+
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(calculate(&nested(MAX_DEPTH - 1)).unwrap(), "1");
+        assert!(calculate(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(calculate(&"-".repeat(MAX_DEPTH + 1)).is_err());
+        assert!(calculate(&"1+".repeat(MAX_INPUT_LENGTH)).is_err());
+    }
+
+    #[test]
+    fn converts_units() {
+        assert_eq!(calculate("0 C in F").unwrap(), "32 °F");
+        assert_eq!(calculate("100 km/h to mph").unwrap(), "62.1371192237 mph");
+        assert_eq!(calculate("1 mi + 1 km in m").unwrap(), "2609.344 m");
+        assert_eq!(calculate("2 * 3 ft in inches").unwrap(), "72 inch");
+        assert_eq!(calculate("1 GiB in MB").unwrap(), "1073.741824 MB");
+        assert!(calculate("1 m + 1 kg").is_err());
+        assert!(calculate("1 m + 1").is_err());
+        assert!(calculate("1 kg in m").is_err());
+        assert!(calculate("5 in m").is_err());
+    }
+
+    #[test]
+    fn remembers_variables_per_user() {
+        let calculator = Calculator::default();
+        let evaluate = |user_id: u64, input: &str| {
+            calculator
+                .evaluate(user_id, input)
+                .map(|evaluation| evaluation.value.to_string())
+        };
+
+        assert_eq!(evaluate(1, "x = 3").unwrap(), "3");
+        assert_eq!(evaluate(1, "x * 2").unwrap(), "6");
+        assert_eq!(evaluate(1, "ans + 1").unwrap(), "7");
+        assert!(evaluate(2, "x").is_err());
+        assert!(evaluate(1, "pi = 3").is_err());
+        assert!(evaluate(1, "ans = 3").is_err());
+        assert_eq!(
+            calculator
+                .variables(1)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["ans", "x"]
+        );
+
+        for i in 1..MAX_VARIABLES {
+            evaluate(1, &format!("v{i} = {i}")).unwrap();
+        }
+        assert!(evaluate(1, "extra = 1").is_err());
+        assert!(evaluate(1, "x = 4").is_ok());
+
+        calculator.clear(1);
+        assert!(evaluate(1, "x").is_err());
+    }
+}
@@ -0,0 +1,306 @@
+use super::units::Unit;
+use super::MAX_DEPTH;
+use anyhow::{bail, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Symbol(char),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let rest = &input[start..];
+            let mut len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+
+            // Scientific notation, as in 1.5e-3
+            if let Some(exponent) = rest[len..].strip_prefix(['e', 'E']) {
+                let sign = usize::from(exponent.starts_with(['+', '-']));
+                let digits = exponent[sign..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(exponent.len() - sign);
+                if digits > 0 {
+                    len += 1 + sign + digits;
+                }
+            }
+
+            let number = &rest[..len];
+            match number.parse() {
+                Ok(number) => tokens.push(Token::Number(number)),
+                Err(_) => bail!("{number} isn't a number"),
+            }
+            while chars.next_if(|&(i, _)| i < start + len).is_some() {}
+        } else if c.is_alphabetic() || c == '_' || c == '°' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || (i == start && c == '°')) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(input[start..end].to_string()));
+        } else if input[start..].starts_with("**") {
+            chars.next();
+            chars.next();
+            tokens.push(Token::Symbol('^'));
+        } else if "+-*/%^(),=".contains(c) {
+            chars.next();
+            tokens.push(Token::Symbol(c));
+        } else {
+            bail!("unexpected {c}");
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    WithUnit(Box<Expr>, &'static Unit),
+}
+
+/// A line of input to the calculator: `[name =] expression [in unit]`.
+#[derive(Debug, PartialEq)]
+pub struct Statement {
+    pub assign_to: Option<String>,
+    pub expr: Expr,
+    pub convert_to: Option<&'static Unit>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_symbol(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<()> {
+        match self.next() {
+            Some(Token::Symbol(c)) if c == symbol => Ok(()),
+            _ => bail!("expected {symbol}"),
+        }
+    }
+
+    /// Guards against input nested so deeply that parsing it would exhaust the stack.
+    fn nest(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            bail!("the expression is nested too deeply");
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        let assign_to = match (self.tokens.first(), self.tokens.get(1)) {
+            (Some(Token::Ident(name)), Some(Token::Symbol('='))) => {
+                let name = name.clone();
+                self.position = 2;
+                Some(name)
+            }
+            _ => None,
+        };
+
+        let expr = self.expr()?;
+
+        let convert_to = match self.next() {
+            None => None,
+            Some(Token::Ident(keyword)) if keyword == "in" || keyword == "to" => {
+                match self.unit() {
+                    Some(unit) => Some(unit),
+                    None => bail!("expected a unit after {keyword}"),
+                }
+            }
+            Some(_) => bail!("unexpected input after the expression"),
+        };
+        if self.peek().is_some() {
+            bail!("unexpected input after the unit");
+        }
+
+        Ok(Statement {
+            assign_to,
+            expr,
+            convert_to,
+        })
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut expr = self.term()?;
+        loop {
+            let op = if self.peek_symbol('+') {
+                BinaryOp::Add
+            } else if self.peek_symbol('-') {
+                BinaryOp::Subtract
+            } else {
+                return Ok(expr);
+            };
+            self.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.peek_symbol('*') {
+                BinaryOp::Multiply
+            } else if self.peek_symbol('/') {
+                BinaryOp::Divide
+            } else if self.peek_symbol('%') {
+                BinaryOp::Remainder
+            } else {
+                return Ok(expr);
+            };
+            self.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let negate = if self.peek_symbol('-') {
+            true
+        } else if self.peek_symbol('+') {
+            false
+        } else {
+            return self.power();
+        };
+        self.next();
+
+        self.nest()?;
+        let expr = self.unary()?;
+        self.depth -= 1;
+        Ok(if negate {
+            Expr::Negate(Box::new(expr))
+        } else {
+            expr
+        })
+    }
+
+    fn power(&mut self) -> Result<Expr> {
+        let base = self.postfix()?;
+        if !self.peek_symbol('^') {
+            return Ok(base);
+        }
+        self.next();
+        // Exponentiation is right associative, and binds tighter than a leading minus.
+        let exponent = self.unary()?;
+        Ok(Expr::Binary(
+            BinaryOp::Power,
+            Box::new(base),
+            Box::new(exponent),
+        ))
+    }
+
+    /// Parses a unit, such as `km` or `m/s`, if one comes next.
+    fn unit(&mut self) -> Option<&'static Unit> {
+        let Some(Token::Ident(name)) = self.peek() else {
+            return None;
+        };
+        if name == "in" || name == "to" {
+            return None;
+        }
+
+        // Units like km/h are tokenized as a division.
+        if let (Some(Token::Symbol('/')), Some(Token::Ident(per))) = (
+            self.tokens.get(self.position + 1),
+            self.tokens.get(self.position + 2),
+        ) {
+            if let Some(unit) = Unit::find(&format!("{name}/{per}")) {
+                self.position += 3;
+                return Some(unit);
+            }
+        }
+
+        let unit = Unit::find(name);
+        if unit.is_some() {
+            self.position += 1;
+        }
+        unit
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let expr = self.primary()?;
+        Ok(match self.unit() {
+            Some(unit) => Expr::WithUnit(Box::new(expr), unit),
+            None => expr,
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::Ident(name)) if self.peek_symbol('(') => {
+                self.next();
+                self.nest()?;
+                let mut args = Vec::new();
+                if !self.peek_symbol(')') {
+                    args.push(self.expr()?);
+                    while self.peek_symbol(',') {
+                        self.next();
+                        args.push(self.expr()?);
+                    }
+                }
+                self.expect_symbol(')')?;
+                self.depth -= 1;
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::Ident(name)) => Ok(Expr::Variable(name)),
+            Some(Token::Symbol('(')) => {
+                self.nest()?;
+                let expr = self.expr()?;
+                self.expect_symbol(')')?;
+                self.depth -= 1;
+                Ok(expr)
+            }
+            Some(Token::Symbol(c)) => bail!("unexpected {c}"),
+            None => bail!("the expression ended unexpectedly"),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Statement> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+        depth: 0,
+    };
+    parser.statement()
+}
@@ -0,0 +1,229 @@
+/// What a unit measures; only units with the same dimension can be converted between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Temperature,
+    Volume,
+    Speed,
+    Data,
+}
+
+/// A unit of measurement, defined by how to convert it to its dimension's base unit.
+#[derive(Debug, PartialEq)]
+pub struct Unit {
+    /// The names the unit is known by, the first of which is used to display it.
+    pub names: &'static [&'static str],
+    pub dimension: Dimension,
+    /// A value in this unit is `value * scale + offset` in the base unit.
+    scale: f64,
+    offset: f64,
+}
+
+impl Unit {
+    const fn new(names: &'static [&'static str], dimension: Dimension, scale: f64) -> Self {
+        Self {
+            names,
+            dimension,
+            scale,
+            offset: 0.,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.names[0]
+    }
+
+    /// Looks up a unit by name, ignoring case if there's no exact match.
+    pub fn find(name: &str) -> Option<&'static Unit> {
+        UNITS
+            .iter()
+            .copied()
+            .find(|unit| unit.names.contains(&name))
+            .or_else(|| {
+                UNITS.iter().copied().find(|unit| {
+                    unit.names
+                        .iter()
+                        .any(|unit_name| unit_name.eq_ignore_ascii_case(name))
+                })
+            })
+    }
+
+    /// Converts a value in this unit to the given unit, which must have the same dimension.
+    pub fn convert(&self, value: f64, to: &Unit) -> f64 {
+        debug_assert_eq!(self.dimension, to.dimension);
+        (value * self.scale + self.offset - to.offset) / to.scale
+    }
+}
+
+pub static METRE: Unit = Unit::new(
+    &["m", "metre", "metres", "meter", "meters"],
+    Dimension::Length,
+    1.,
+);
+pub static KILOMETRE: Unit = Unit::new(
+    &["km", "kilometre", "kilometres", "kilometer", "kilometers"],
+    Dimension::Length,
+    1000.,
+);
+pub static CENTIMETRE: Unit = Unit::new(
+    &[
+        "cm",
+        "centimetre",
+        "centimetres",
+        "centimeter",
+        "centimeters",
+    ],
+    Dimension::Length,
+    0.01,
+);
+pub static MILLIMETRE: Unit = Unit::new(
+    &[
+        "mm",
+        "millimetre",
+        "millimetres",
+        "millimeter",
+        "millimeters",
+    ],
+    Dimension::Length,
+    0.001,
+);
+pub static MILE: Unit = Unit::new(&["mi", "mile", "miles"], Dimension::Length, 1609.344);
+pub static YARD: Unit = Unit::new(&["yd", "yard", "yards"], Dimension::Length, 0.9144);
+pub static FOOT: Unit = Unit::new(&["ft", "foot", "feet"], Dimension::Length, 0.3048);
+// `in` is left out, since it's how conversions are written.
+pub static INCH: Unit = Unit::new(&["inch", "inches"], Dimension::Length, 0.0254);
+
+pub static KILOGRAM: Unit = Unit::new(
+    &["kg", "kilogram", "kilograms", "kilo", "kilos"],
+    Dimension::Mass,
+    1.,
+);
+pub static GRAM: Unit = Unit::new(&["g", "gram", "grams"], Dimension::Mass, 0.001);
+pub static TONNE: Unit = Unit::new(&["t", "tonne", "tonnes"], Dimension::Mass, 1000.);
+pub static POUND: Unit = Unit::new(
+    &["lb", "lbs", "pound", "pounds"],
+    Dimension::Mass,
+    0.45359237,
+);
+pub static OUNCE: Unit = Unit::new(&["oz", "ounce", "ounces"], Dimension::Mass, 0.028349523125);
+pub static STONE: Unit = Unit::new(&["st", "stone", "stones"], Dimension::Mass, 6.35029318);
+
+pub static SECOND: Unit = Unit::new(
+    &["s", "sec", "secs", "second", "seconds"],
+    Dimension::Time,
+    1.,
+);
+pub static MILLISECOND: Unit = Unit::new(
+    &["ms", "millisecond", "milliseconds"],
+    Dimension::Time,
+    0.001,
+);
+pub static MINUTE: Unit = Unit::new(&["min", "mins", "minute", "minutes"], Dimension::Time, 60.);
+pub static HOUR: Unit = Unit::new(&["h", "hr", "hrs", "hour", "hours"], Dimension::Time, 3600.);
+pub static DAY: Unit = Unit::new(&["day", "days"], Dimension::Time, 86400.);
+pub static WEEK: Unit = Unit::new(&["week", "weeks"], Dimension::Time, 604800.);
+pub static YEAR: Unit = Unit::new(&["year", "years"], Dimension::Time, 31557600.);
+
+pub static KELVIN: Unit = Unit::new(&["K", "kelvin"], Dimension::Temperature, 1.);
+pub static CELSIUS: Unit = Unit {
+    names: &["°C", "C", "celsius"],
+    dimension: Dimension::Temperature,
+    scale: 1.,
+    offset: 273.15,
+};
+pub static FAHRENHEIT: Unit = Unit {
+    names: &["°F", "F", "fahrenheit"],
+    dimension: Dimension::Temperature,
+    scale: 5. / 9.,
+    offset: 459.67 * 5. / 9.,
+};
+
+pub static LITRE: Unit = Unit::new(
+    &["L", "l", "litre", "litres", "liter", "liters"],
+    Dimension::Volume,
+    1.,
+);
+pub static MILLILITRE: Unit = Unit::new(
+    &[
+        "mL",
+        "ml",
+        "millilitre",
+        "millilitres",
+        "milliliter",
+        "milliliters",
+    ],
+    Dimension::Volume,
+    0.001,
+);
+pub static GALLON: Unit = Unit::new(
+    &["gal", "gallon", "gallons"],
+    Dimension::Volume,
+    3.785411784,
+);
+pub static PINT: Unit = Unit::new(&["pt", "pint", "pints"], Dimension::Volume, 0.473176473);
+pub static CUP: Unit = Unit::new(&["cup", "cups"], Dimension::Volume, 0.2365882365);
+pub static FLUID_OUNCE: Unit = Unit::new(&["floz"], Dimension::Volume, 0.0295735295625);
+
+pub static METRES_PER_SECOND: Unit = Unit::new(&["m/s", "mps"], Dimension::Speed, 1.);
+pub static KILOMETRES_PER_HOUR: Unit =
+    Unit::new(&["km/h", "kph", "kmh"], Dimension::Speed, 1. / 3.6);
+pub static MILES_PER_HOUR: Unit = Unit::new(&["mph", "mi/h"], Dimension::Speed, 0.44704);
+pub static KNOT: Unit = Unit::new(&["kn", "knot", "knots"], Dimension::Speed, 1852. / 3600.);
+
+pub static BYTE: Unit = Unit::new(&["B", "byte", "bytes"], Dimension::Data, 1.);
+pub static BIT: Unit = Unit::new(&["bit", "bits"], Dimension::Data, 0.125);
+pub static KILOBYTE: Unit = Unit::new(&["kB", "KB"], Dimension::Data, 1e3);
+pub static MEGABYTE: Unit = Unit::new(&["MB"], Dimension::Data, 1e6);
+pub static GIGABYTE: Unit = Unit::new(&["GB"], Dimension::Data, 1e9);
+pub static TERABYTE: Unit = Unit::new(&["TB"], Dimension::Data, 1e12);
+pub static KIBIBYTE: Unit = Unit::new(&["KiB"], Dimension::Data, 1024.);
+pub static MEBIBYTE: Unit = Unit::new(&["MiB"], Dimension::Data, 1048576.);
+pub static GIBIBYTE: Unit = Unit::new(&["GiB"], Dimension::Data, 1073741824.);
+
+static UNITS: &[&Unit] = &[
+    &METRE,
+    &KILOMETRE,
+    &CENTIMETRE,
+    &MILLIMETRE,
+    &MILE,
+    &YARD,
+    &FOOT,
+    &INCH,
+    &KILOGRAM,
+    &GRAM,
+    &TONNE,
+    &POUND,
+    &OUNCE,
+    &STONE,
+    &SECOND,
+    &MILLISECOND,
+    &MINUTE,
+    &HOUR,
+    &DAY,
+    &WEEK,
+    &YEAR,
+    &KELVIN,
+    &CELSIUS,
+    &FAHRENHEIT,
+    &LITRE,
+    &MILLILITRE,
+    &GALLON,
+    &PINT,
+    &CUP,
+    &FLUID_OUNCE,
+    &METRES_PER_SECOND,
+    &KILOMETRES_PER_HOUR,
+    &MILES_PER_HOUR,
+    &KNOT,
+    &BYTE,
+    &BIT,
+    &KILOBYTE,
+    &MEGABYTE,
+    &GIGABYTE,
+    &TERABYTE,
+    &KIBIBYTE,
+    &MEBIBYTE,
+    &GIBIBYTE,
+];
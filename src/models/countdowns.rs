@@ -8,9 +8,11 @@ use std::ops::Sub;
 #[allow(dead_code)]
 pub struct Countdown {
     id: i64,
-    end: i64,
+    pub end: i64,
     active: bool,
-    guild: i64,
+    pub guild: i64,
+    /// Where the countdown is announced when it ends, if not the guild's default.
+    pub channel: Option<i64>,
 }
 
 impl Countdown {
@@ -23,6 +25,11 @@ impl Countdown {
 
         format!("***S{}*** is {:#}.", self.id, HumanTime::from(duration),)
     }
+
+    /// The message posted when the countdown ends.
+    pub fn announcement(&self) -> String {
+        format!("***S{}*** is here!", self.id)
+    }
 }
 
 pub struct CountdownStore<'pool> {
@@ -34,15 +41,17 @@ impl<'pool> CountdownStore<'pool> {
         Self { pool }
     }
 
-    /// Inserts a new countdown with the given end timestamp into the database.
-    pub async fn insert(&self, timestamp: i64, guild_id: i64) -> Result<()> {
+    /// Inserts a new countdown with the given end timestamp into the database.  It is
+    /// announced in `channel` when it ends, or in the guild's default countdown channel.
+    pub async fn insert(&self, timestamp: i64, guild_id: i64, channel: Option<i64>) -> Result<()> {
         sqlx::query!(
             "
-        INSERT INTO countdowns (end, active, guild)
-        VALUES (?, true, ?)
+        INSERT INTO countdowns (end, active, guild, channel)
+        VALUES (?, true, ?, ?)
             ",
             timestamp,
-            guild_id
+            guild_id,
+            channel
         )
         .execute(self.pool)
         .await
@@ -61,7 +70,7 @@ impl<'pool> CountdownStore<'pool> {
         sqlx::query_as!(
             Countdown,
             r#"
-        SELECT id as "id!", end as "end!", active as "active!", guild as "guild!"
+        SELECT id as "id!", end as "end!", active as "active!", guild as "guild!", channel
         FROM countdowns
         WHERE end >= ?
         AND guild = ?
@@ -87,4 +96,65 @@ impl<'pool> CountdownStore<'pool> {
             .await
             .map(|mut cs| cs.pop())
     }
+
+    /// Returns the active countdowns which have ended, across every guild.
+    pub async fn ended(&self, timestamp: i64) -> Result<Vec<Countdown>> {
+        sqlx::query_as!(
+            Countdown,
+            r#"
+        SELECT id as "id!", end as "end!", active as "active!", guild as "guild!", channel
+        FROM countdowns
+        WHERE end <= ?
+        AND active = true
+        ORDER BY end ASC, id ASC
+            "#,
+            timestamp,
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|_| anyhow!("failed to get countdowns ending by {timestamp}"))
+    }
+
+    /// Marks a countdown as over, so that it isn't announced again.
+    pub async fn finish(&self, countdown: &Countdown) -> Result<()> {
+        sqlx::query!(
+            "UPDATE countdowns SET active = false WHERE id = ?",
+            countdown.id
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .map_err(|_| anyhow!("failed to finish countdown {}", countdown.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_pool;
+
+    #[tokio::test]
+    async fn finishes_ended_countdowns() {
+        let pool = test_pool().await;
+        let store = CountdownStore::new(&pool);
+        store.insert(100, 1, Some(10)).await.unwrap();
+        store.insert(200, 2, None).await.unwrap();
+        store.insert(300, 1, None).await.unwrap();
+
+        let ended = store.ended(250).await.unwrap();
+        assert_eq!(
+            ended
+                .iter()
+                .map(|countdown| (countdown.end, countdown.guild, countdown.channel))
+                .collect::<Vec<_>>(),
+            [(100, 1, Some(10)), (200, 2, None)]
+        );
+
+        store.finish(&ended[0]).await.unwrap();
+        let ended = store.ended(250).await.unwrap();
+        assert_eq!(ended.len(), 1);
+        assert_eq!(ended[0].end, 200);
+
+        assert_eq!(store.get_after(0, 1, 5).await.unwrap().len(), 1);
+    }
 }
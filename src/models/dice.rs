@@ -0,0 +1,709 @@
+use crate::models::probability::Pmf;
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+const MAX_DICE: usize = 100;
+const MAX_SIDES: u32 = 1000;
+const MAX_TERMS: usize = 20;
+/// The most times an expression can be rolled at once, e.g. `3d6 x6`.
+const MAX_REPEATS: usize = 20;
+/// How many times a single exploding die can explode, so that a run of luck can't go on
+/// forever.
+const MAX_EXPLOSIONS: usize = 10;
+/// The most outcomes enumerated when working out the distribution of kept dice.
+const MAX_OUTCOMES: usize = 1_000_000;
+/// The widest range of totals convolved when working out the distribution of an
+/// expression.
+const MAX_RANGE: i64 = 10_000;
+
+/// Which of the dice in a roll count towards its total.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Keep {
+    Highest(usize),
+    Lowest(usize),
+}
+
+/// A number of identical dice, such as `4d6kh3` or `8d10!`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dice {
+    pub count: usize,
+    pub sides: u32,
+    /// Whether a die rolling its highest face is rolled again and added on.
+    pub explode: bool,
+    pub keep: Option<Keep>,
+}
+
+/// A single die in a roll.  Exploding dice have a roll for each time they were rolled.
+#[derive(Clone, Debug)]
+pub struct Die {
+    pub rolls: Vec<u32>,
+    pub kept: bool,
+}
+
+impl Die {
+    pub fn total(&self) -> i64 {
+        self.rolls.iter().map(|roll| i64::from(*roll)).sum()
+    }
+}
+
+impl Dice {
+    fn roll_die<R: Rng + ?Sized>(&self, rng: &mut R) -> Die {
+        let mut rolls = vec![rng.gen_range(1..=self.sides)];
+        while self.explode && rolls.len() <= MAX_EXPLOSIONS && rolls.last() == Some(&self.sides) {
+            rolls.push(rng.gen_range(1..=self.sides));
+        }
+
+        Die { rolls, kept: true }
+    }
+
+    /// Rolls the dice, marking any which aren't kept.  The dice stay in the order they
+    /// were rolled.
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<Die> {
+        let mut dice = (0..self.count)
+            .map(|_| self.roll_die(rng))
+            .collect::<Vec<_>>();
+
+        if let Some(keep) = self.keep {
+            let mut order = (0..dice.len()).collect::<Vec<_>>();
+            order.sort_by_key(|i| dice[*i].total());
+            let dropped = match keep {
+                Keep::Highest(n) => &order[..self.count - n],
+                Keep::Lowest(n) => &order[n..],
+            };
+            for i in dropped {
+                dice[*i].kept = false;
+            }
+        }
+
+        dice
+    }
+
+    /// Returns the distribution of a single die.
+    fn die_distribution(&self) -> Pmf {
+        let sides = i64::from(self.sides);
+        if !self.explode {
+            return Pmf::uniform(1, sides);
+        }
+
+        // A die which explodes k times is k highest faces followed by a lower one, unless
+        // it has run out of explosions, in which case the last roll can be anything.
+        let p = 1. / sides as f64;
+        let mut masses = Vec::new();
+        for explosions in 0..=MAX_EXPLOSIONS {
+            let mass = p.powi(explosions as i32 + 1);
+            let last = if explosions == MAX_EXPLOSIONS {
+                sides
+            } else {
+                sides - 1
+            };
+            for roll in 1..=last {
+                masses.push((explosions as i64 * sides + roll, mass));
+            }
+        }
+
+        Pmf::from_masses(masses)
+    }
+
+    /// Returns the difference between the highest and lowest totals of the kept dice.
+    fn range(&self) -> i64 {
+        let sides = i64::from(self.sides);
+        let highest_roll = if self.explode {
+            sides * (MAX_EXPLOSIONS as i64 + 1)
+        } else {
+            sides
+        };
+        let kept = match self.keep {
+            Some(Keep::Highest(n) | Keep::Lowest(n)) => n,
+            None => self.count,
+        };
+
+        kept as i64 * (highest_roll - 1)
+    }
+
+    /// Returns how many outcomes have to be gone through to work out the distribution,
+    /// which is none unless some dice are dropped.
+    fn outcomes(&self) -> usize {
+        if self.keep.is_none() {
+            return 0;
+        }
+
+        let sides = self.sides as usize;
+        let faces = if self.explode {
+            MAX_EXPLOSIONS * (sides - 1) + sides
+        } else {
+            sides
+        };
+        faces.saturating_pow(self.count as u32)
+    }
+
+    /// Returns the exact distribution of the total of the kept dice.
+    pub fn distribution(&self) -> Result<Pmf> {
+        if self.range() > MAX_RANGE {
+            bail!("{self} has too many possible totals to work out");
+        }
+        if self.outcomes() > MAX_OUTCOMES {
+            bail!("{self} has too many possible outcomes to work out");
+        }
+
+        let die = self.die_distribution();
+        let Some(keep) = self.keep else {
+            return Ok((1..self.count).fold(die.clone(), |total, _| total.add(&die)));
+        };
+
+        // Which dice are kept depends on all of them, so go through every outcome.
+        let faces = die.masses().collect::<Vec<_>>();
+
+        let mut masses = Vec::new();
+        let mut indices = vec![0; self.count];
+        let mut values = Vec::with_capacity(self.count);
+        loop {
+            values.clear();
+            values.extend(indices.iter().map(|i| faces[*i].0));
+            values.sort_unstable();
+            let kept = match keep {
+                Keep::Highest(n) => &values[self.count - n..],
+                Keep::Lowest(n) => &values[..n],
+            };
+            let mass = indices.iter().map(|i| faces[*i].1).product::<f64>();
+            masses.push((kept.iter().sum::<i64>(), mass));
+
+            // Move on to the next outcome, like an odometer.
+            let Some(position) = indices.iter().position(|i| *i + 1 < faces.len()) else {
+                break;
+            };
+            indices[..position].fill(0);
+            indices[position] += 1;
+        }
+
+        Ok(Pmf::from_masses(masses))
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        if self.explode {
+            write!(f, "!")?;
+        }
+        match self.keep {
+            Some(Keep::Highest(n)) => write!(f, "kh{n}"),
+            Some(Keep::Lowest(n)) => write!(f, "kl{n}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Term {
+    Dice(Dice),
+    Constant(i64),
+}
+
+/// A sum of dice and constants, such as `4d6kh3+2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiceExpr {
+    /// Each term along with its sign, either 1 or -1.
+    terms: Vec<(i64, Term)>,
+}
+
+impl DiceExpr {
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> RollResult {
+        let terms = self
+            .terms
+            .iter()
+            .map(|(sign, term)| {
+                let rolled = match term {
+                    Term::Dice(dice) => RolledTerm::Dice(dice.roll(rng)),
+                    Term::Constant(value) => RolledTerm::Constant(*value),
+                };
+                (*sign, rolled)
+            })
+            .collect();
+
+        RollResult { terms }
+    }
+
+    /// Returns the exact distribution of the expression's total.
+    pub fn distribution(&self) -> Result<Pmf> {
+        // Each term could be worked out on its own, but the limits apply to the whole
+        // expression so that adding many terms together can't take too long either.
+        let dice = self.terms.iter().filter_map(|(_, term)| match term {
+            Term::Dice(dice) => Some(dice),
+            Term::Constant(_) => None,
+        });
+        if dice.clone().map(Dice::range).sum::<i64>() > MAX_RANGE {
+            bail!("{self} has too many possible totals to work out");
+        }
+        if dice.map(Dice::outcomes).fold(0, usize::saturating_add) > MAX_OUTCOMES {
+            bail!("{self} has too many possible outcomes to work out");
+        }
+
+        let mut total = Pmf::constant(0);
+        for (sign, term) in &self.terms {
+            let pmf = match term {
+                Term::Dice(dice) => dice.distribution()?,
+                Term::Constant(value) => Pmf::constant(*value),
+            };
+            total = if *sign < 0 {
+                total.add(&pmf.negate())
+            } else {
+                total.add(&pmf)
+            };
+        }
+
+        Ok(total)
+    }
+}
+
+/// Parses `<expression> [xN]` into the expression and the number of times to roll it.
+pub fn parse_repeated(input: &str) -> Result<(DiceExpr, usize)> {
+    let mut parts = input.split_whitespace().collect::<Vec<_>>();
+
+    let mut repeats = 1;
+    if let Some(n) = parts
+        .last()
+        .and_then(|last| last.strip_prefix('x'))
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        if n == 0 || n > MAX_REPEATS {
+            bail!("you can roll between 1 and {MAX_REPEATS} times at once");
+        }
+        repeats = n;
+        parts.pop();
+    }
+
+    if parts.is_empty() {
+        bail!("give some dice to roll, e.g. `~roll 4d6kh3+2`");
+    }
+
+    Ok((parts.concat().parse()?, repeats))
+}
+
+/// Splits off the number at the start of the input, if there is one.
+fn take_number(input: &str) -> Result<(Option<u32>, &str)> {
+    let end = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    if end == 0 {
+        return Ok((None, input));
+    }
+
+    let (number, rest) = input.split_at(end);
+    let number = number
+        .parse()
+        .with_context(|| format!("{number} is too big"))?;
+    Ok((Some(number), rest))
+}
+
+/// A modifier which keeps or drops some of the dice.
+enum Selection {
+    KeepHighest(usize),
+    KeepLowest(usize),
+    DropHighest(usize),
+    DropLowest(usize),
+}
+
+fn parse_term(input: &str) -> Result<(Term, &str)> {
+    let (count, rest) = take_number(input)?;
+    let Some(rest) = rest.strip_prefix('d') else {
+        return match count {
+            Some(value) => Ok((Term::Constant(value.into()), rest)),
+            None => bail!("expected a number or dice at {input:?}"),
+        };
+    };
+
+    let (sides, mut rest) = match rest.strip_prefix('%') {
+        Some(rest) => (Some(100), rest),
+        None => take_number(rest)?,
+    };
+    let sides = sides.with_context(|| "dice need a number of sides")?;
+
+    let mut count = count.map(|count| count as usize);
+    let mut explode = false;
+    let mut selection = None;
+    loop {
+        if let Some(after) = rest.strip_prefix('!') {
+            explode = true;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("adv") {
+            count.get_or_insert(2);
+            selection = Some(Selection::KeepHighest(1));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("dis") {
+            count.get_or_insert(2);
+            selection = Some(Selection::KeepLowest(1));
+            rest = after;
+        } else if let Some((modifier, after)) = ["kh", "kl", "dh", "dl", "k"]
+            .iter()
+            .find_map(|modifier| Some((*modifier, rest.strip_prefix(modifier)?)))
+        {
+            let (n, after) = take_number(after)?;
+            let n = n.unwrap_or(1) as usize;
+            selection = Some(match modifier {
+                "kl" => Selection::KeepLowest(n),
+                "dh" => Selection::DropHighest(n),
+                "dl" => Selection::DropLowest(n),
+                _ => Selection::KeepHighest(n),
+            });
+            rest = after;
+        } else {
+            break;
+        }
+    }
+
+    let count = count.unwrap_or(1);
+    if count == 0 || count > MAX_DICE {
+        bail!("you can roll between 1 and {MAX_DICE} dice at a time");
+    }
+    if sides == 0 || sides > MAX_SIDES {
+        bail!("dice can have between 1 and {MAX_SIDES} sides");
+    }
+    if explode && sides == 1 {
+        bail!("a d1 can't explode");
+    }
+
+    let keep = match selection {
+        Some(
+            Selection::KeepHighest(n)
+            | Selection::KeepLowest(n)
+            | Selection::DropHighest(n)
+            | Selection::DropLowest(n),
+        ) if n > count => bail!("can't keep or drop {n} of {count} dice"),
+        Some(Selection::KeepHighest(n)) => Some(Keep::Highest(n)),
+        Some(Selection::KeepLowest(n)) => Some(Keep::Lowest(n)),
+        Some(Selection::DropHighest(n)) => Some(Keep::Lowest(count - n)),
+        Some(Selection::DropLowest(n)) => Some(Keep::Highest(count - n)),
+        None => None,
+    };
+
+    let dice = Dice {
+        count,
+        sides,
+        explode,
+        keep,
+    };
+    Ok((Term::Dice(dice), rest))
+}
+
+impl FromStr for DiceExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let input = s
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+
+        let mut rest = input.as_str();
+        let mut terms = Vec::new();
+        let mut sign = match rest.strip_prefix('-') {
+            Some(after) => {
+                rest = after;
+                -1
+            }
+            None => 1,
+        };
+        loop {
+            let (term, after) = parse_term(rest)?;
+            terms.push((sign, term));
+            if terms.len() > MAX_TERMS {
+                bail!("dice expressions can have at most {MAX_TERMS} terms");
+            }
+
+            let mut chars = after.chars();
+            sign = match chars.next() {
+                Some('+') => 1,
+                Some('-') => -1,
+                Some(c) => bail!("unexpected {c} in dice expression"),
+                None => break,
+            };
+            rest = chars.as_str();
+        }
+
+        Ok(Self { terms })
+    }
+}
+
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (sign, term)) in self.terms.iter().enumerate() {
+            match (i, *sign < 0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            match term {
+                Term::Dice(dice) => write!(f, "{dice}")?,
+                Term::Constant(value) => write!(f, "{value}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum RolledTerm {
+    Dice(Vec<Die>),
+    Constant(i64),
+}
+
+impl RolledTerm {
+    pub fn total(&self) -> i64 {
+        match self {
+            RolledTerm::Dice(dice) => dice.iter().filter(|die| die.kept).map(Die::total).sum(),
+            RolledTerm::Constant(value) => *value,
+        }
+    }
+}
+
+/// The outcome of rolling a dice expression.
+#[derive(Clone, Debug)]
+pub struct RollResult {
+    terms: Vec<(i64, RolledTerm)>,
+}
+
+impl RollResult {
+    pub fn total(&self) -> i64 {
+        self.terms
+            .iter()
+            .map(|(sign, term)| sign * term.total())
+            .sum()
+    }
+}
+
+/// Shows each die, with exploded rolls marked by `!` and dropped dice struck through.
+impl fmt::Display for RollResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (sign, term)) in self.terms.iter().enumerate() {
+            match (i, *sign < 0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+
+            let RolledTerm::Dice(dice) = term else {
+                write!(f, "{}", term.total())?;
+                continue;
+            };
+            let dice = dice
+                .iter()
+                .map(|die| {
+                    let last = die.rolls.len() - 1;
+                    let rolls = die
+                        .rolls
+                        .iter()
+                        .enumerate()
+                        .map(|(i, roll)| {
+                            if i < last {
+                                format!("{roll}!")
+                            } else {
+                                roll.to_string()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("+");
+                    if die.kept {
+                        rolls
+                    } else {
+                        format!("~~{rolls}~~")
+                    }
+                })
+                .collect::<Vec<_>>();
+            write!(f, "[{}]", dice.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn dice(expr: &DiceExpr) -> Vec<&Dice> {
+        expr.terms
+            .iter()
+            .filter_map(|(_, term)| match term {
+                Term::Dice(dice) => Some(dice),
+                Term::Constant(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_expressions() {
+        let expr = "4d6kh3 + 2".parse::<DiceExpr>().unwrap();
+        assert_eq!(
+            expr.terms,
+            vec![
+                (
+                    1,
+                    Term::Dice(Dice {
+                        count: 4,
+                        sides: 6,
+                        explode: false,
+                        keep: Some(Keep::Highest(3)),
+                    })
+                ),
+                (1, Term::Constant(2)),
+            ]
+        );
+        assert_eq!(expr.to_string(), "4d6kh3 + 2");
+
+        let expr = "2d20adv".parse::<DiceExpr>().unwrap();
+        assert_eq!(dice(&expr)[0].keep, Some(Keep::Highest(1)));
+        assert_eq!(dice(&expr)[0].count, 2);
+        assert_eq!("d20dis".parse::<DiceExpr>().unwrap().to_string(), "2d20kl1");
+
+        let expr = "8d10!".parse::<DiceExpr>().unwrap();
+        assert!(dice(&expr)[0].explode);
+        assert_eq!(expr.to_string(), "8d10!");
+
+        assert_eq!(
+            "-d% - 4d6dl1".parse::<DiceExpr>().unwrap().to_string(),
+            "-1d100 - 4d6kh3"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for input in [
+            "", "d", "4d", "0d6", "101d6", "1d1001", "1d1!", "2d6kh3", "2d6*2",
+        ] {
+            assert!(input.parse::<DiceExpr>().is_err(), "{input} was accepted");
+        }
+        assert!(vec!["1"; MAX_TERMS + 1]
+            .join("+")
+            .parse::<DiceExpr>()
+            .is_err());
+    }
+
+    #[test]
+    fn parses_repeats() {
+        let (expr, repeats) = parse_repeated("4d6kh3 x6").unwrap();
+        assert_eq!(expr.to_string(), "4d6kh3");
+        assert_eq!(repeats, 6);
+
+        let (expr, repeats) = parse_repeated("1d6 + 2").unwrap();
+        assert_eq!(expr.to_string(), "1d6 + 2");
+        assert_eq!(repeats, 1);
+
+        assert!(parse_repeated("1d6 x0").is_err());
+        assert!(parse_repeated("1d6 x21").is_err());
+        assert!(parse_repeated("x6").is_err());
+    }
+
+    #[test]
+    fn rolls_are_deterministic_for_a_seed() {
+        let expr = "4d6kh3 + 8d10! - 2".parse::<DiceExpr>().unwrap();
+        let first = expr.roll(&mut StdRng::seed_from_u64(7));
+        let second = expr.roll(&mut StdRng::seed_from_u64(7));
+
+        assert_eq!(first.to_string(), second.to_string());
+        assert_eq!(first.total(), second.total());
+    }
+
+    #[test]
+    fn marks_kept_and_exploded_dice() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let keep_highest = Dice {
+            count: 4,
+            sides: 6,
+            explode: false,
+            keep: Some(Keep::Highest(3)),
+        };
+        let exploding = Dice {
+            count: 50,
+            sides: 2,
+            explode: true,
+            keep: None,
+        };
+
+        for _ in 0..100 {
+            let rolled = keep_highest.roll(&mut rng);
+            let (kept, dropped): (Vec<_>, Vec<_>) = rolled.iter().partition(|die| die.kept);
+            assert_eq!(kept.len(), 3);
+            assert!(kept.iter().all(|die| die.total() >= dropped[0].total()));
+
+            for die in exploding.roll(&mut rng) {
+                let (last, exploded) = die.rolls.split_last().unwrap();
+                assert!(exploded.iter().all(|roll| *roll == 2));
+                assert!(*last == 1 || die.rolls.len() == MAX_EXPLOSIONS + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn displays_rolls() {
+        let result = RollResult {
+            terms: vec![
+                (
+                    1,
+                    RolledTerm::Dice(vec![
+                        Die {
+                            rolls: vec![6, 2],
+                            kept: true,
+                        },
+                        Die {
+                            rolls: vec![1],
+                            kept: false,
+                        },
+                    ]),
+                ),
+                (-1, RolledTerm::Constant(2)),
+            ],
+        };
+
+        assert_eq!(result.to_string(), "[6!+2, ~~1~~] - 2");
+        assert_eq!(result.total(), 6);
+    }
+
+    #[test]
+    fn works_out_exact_distributions() {
+        let distribution = |input: &str| input.parse::<DiceExpr>().unwrap().distribution().unwrap();
+
+        let two_d6 = distribution("2d6");
+        assert!((two_d6.mass(7) - 1. / 6.).abs() < 1e-12);
+
+        let ability_score = distribution("4d6kh3");
+        assert_eq!((ability_score.min(), ability_score.max()), (3, 18));
+        assert!((ability_score.mean() - 12.2446).abs() < 1e-4);
+        assert!((ability_score.mass(18) - 21. / 1296.).abs() < 1e-12);
+
+        assert!((distribution("2d20adv").mean() - 13.825).abs() < 1e-12);
+        assert!((distribution("2d20dis").mean() - 7.175).abs() < 1e-12);
+        // An uncapped exploding d6 averages 3.5 × 6/5.
+        assert!((distribution("1d6!").mean() - 4.2).abs() < 1e-6);
+
+        let expr = distribution("2d6 - 1d4 + 3");
+        assert_eq!((expr.min(), expr.max()), (1, 14));
+        assert!((expr.cdf(expr.max()) - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn limits_the_work_for_whole_expressions() {
+        assert!("50d100".parse::<DiceExpr>().unwrap().distribution().is_ok());
+        assert!("100d100+100d100"
+            .parse::<DiceExpr>()
+            .unwrap()
+            .distribution()
+            .is_err());
+        assert!(vec!["100d100"; MAX_TERMS]
+            .join("+")
+            .parse::<DiceExpr>()
+            .unwrap()
+            .distribution()
+            .is_err());
+        assert!("6d10kh1+6d10kh1"
+            .parse::<DiceExpr>()
+            .unwrap()
+            .distribution()
+            .is_err());
+    }
+}
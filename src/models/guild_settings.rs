@@ -0,0 +1,349 @@
+use anyhow::{anyhow, bail, Context, Result};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// The prefix used in DMs, and in guilds which haven't set their own.
+pub const DEFAULT_PREFIX: &str = "~";
+const MAX_PREFIX_LENGTH: usize = 5;
+
+/// A setting which can be changed with `~config`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Prefix,
+    DisabledCommands,
+    DisabledCategories,
+    CountdownChannel,
+    Locale,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 5] = [
+        Setting::Prefix,
+        Setting::DisabledCommands,
+        Setting::DisabledCategories,
+        Setting::CountdownChannel,
+        Setting::Locale,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Setting::Prefix => "prefix",
+            Setting::DisabledCommands => "disabled_commands",
+            Setting::DisabledCategories => "disabled_categories",
+            Setting::CountdownChannel => "countdown_channel",
+            Setting::Locale => "locale",
+        }
+    }
+}
+
+impl FromStr for Setting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Setting::ALL
+            .into_iter()
+            .find(|setting| setting.as_str() == s.to_lowercase())
+            .ok_or_else(|| anyhow!("unknown setting {s}"))
+    }
+}
+
+/// How a guild has configured the bot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuildConfig {
+    pub prefix: Option<String>,
+    /// Names of commands which can't be used in the guild, in lowercase.
+    pub disabled_commands: Vec<String>,
+    /// Names of command groups which can't be used in the guild, in lowercase.
+    pub disabled_categories: Vec<String>,
+    /// Where countdowns are announced unless they say otherwise.
+    pub countdown_channel: Option<i64>,
+    /// A locale such as `en-GB`.
+    pub locale: Option<String>,
+}
+
+impl GuildConfig {
+    pub fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or(DEFAULT_PREFIX)
+    }
+
+    pub fn is_disabled(&self, command: &str, category: Option<&str>) -> bool {
+        let command = command.to_lowercase();
+        let category = category.map(str::to_lowercase);
+        self.disabled_commands.contains(&command)
+            || category.is_some_and(|category| self.disabled_categories.contains(&category))
+    }
+
+    /// Returns the given setting's value for display.
+    pub fn describe(&self, setting: Setting) -> String {
+        let list = |names: &[String]| {
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        };
+
+        match setting {
+            Setting::Prefix => format!("`{}`", self.prefix()),
+            Setting::DisabledCommands => list(&self.disabled_commands),
+            Setting::DisabledCategories => list(&self.disabled_categories),
+            Setting::CountdownChannel => self
+                .countdown_channel
+                .map_or("none".to_string(), |channel| format!("<#{channel}>")),
+            Setting::Locale => self.locale.clone().unwrap_or("none".to_string()),
+        }
+    }
+
+    /// Parses and applies a new value for a setting.  Commands and categories are given
+    /// as a list separated by spaces or commas, and have already been checked to exist.
+    pub fn set(&mut self, setting: Setting, value: &str) -> Result<()> {
+        let value = value.trim();
+        let list = || {
+            value
+                .split([' ', ','])
+                .filter(|name| !name.is_empty())
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>()
+        };
+
+        match setting {
+            Setting::Prefix => {
+                if value.is_empty()
+                    || value.chars().count() > MAX_PREFIX_LENGTH
+                    || value.contains(char::is_whitespace)
+                {
+                    bail!("prefixes must be 1 to {MAX_PREFIX_LENGTH} characters, without spaces");
+                }
+                self.prefix = Some(value.to_string());
+            }
+            Setting::DisabledCommands => self.disabled_commands = list(),
+            Setting::DisabledCategories => self.disabled_categories = list(),
+            Setting::CountdownChannel => {
+                let channel = value
+                    .trim_start_matches("<#")
+                    .trim_end_matches('>')
+                    .parse::<i64>()
+                    .with_context(|| format!("{value} isn't a channel"))?;
+                self.countdown_channel = Some(channel);
+            }
+            Setting::Locale => {
+                let mut parts = value.split(['-', '_']);
+                let language = parts.next().unwrap_or_default();
+                let region = parts.next();
+                let is_valid_part = |part: &str| {
+                    (2..=3).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric())
+                };
+                let is_valid = is_valid_part(language)
+                    && language.chars().all(|c| c.is_ascii_alphabetic())
+                    && region.into_iter().all(is_valid_part)
+                    && parts.next().is_none();
+                if !is_valid {
+                    bail!("{value} isn't a locale like en or en-GB");
+                }
+                self.locale = Some(match region {
+                    Some(region) => {
+                        format!("{}-{}", language.to_lowercase(), region.to_uppercase())
+                    }
+                    None => language.to_lowercase(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Puts a setting back to its default.
+    pub fn reset(&mut self, setting: Setting) {
+        let default = GuildConfig::default();
+        match setting {
+            Setting::Prefix => self.prefix = default.prefix,
+            Setting::DisabledCommands => self.disabled_commands = default.disabled_commands,
+            Setting::DisabledCategories => self.disabled_categories = default.disabled_categories,
+            Setting::CountdownChannel => self.countdown_channel = default.countdown_channel,
+            Setting::Locale => self.locale = default.locale,
+        }
+    }
+}
+
+struct GuildSettingsRow {
+    prefix: Option<String>,
+    disabled_commands: String,
+    disabled_categories: String,
+    countdown_channel: Option<i64>,
+    locale: Option<String>,
+}
+
+impl From<GuildSettingsRow> for GuildConfig {
+    fn from(row: GuildSettingsRow) -> Self {
+        let list = |names: String| {
+            names
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        Self {
+            prefix: row.prefix,
+            disabled_commands: list(row.disabled_commands),
+            disabled_categories: list(row.disabled_categories),
+            countdown_channel: row.countdown_channel,
+            locale: row.locale,
+        }
+    }
+}
+
+/// Stores each guild's configuration, caching it since the prefix is needed for every
+/// message.
+pub struct GuildSettings<'pool> {
+    pool: &'pool Pool<Sqlite>,
+    cache: Mutex<HashMap<i64, GuildConfig>>,
+}
+
+impl<'pool> GuildSettings<'pool> {
+    pub fn new(pool: &'pool Pool<Sqlite>) -> Self {
+        Self {
+            pool,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the given guild's configuration, which is the default if it hasn't set any.
+    pub async fn get(&self, guild: i64) -> Result<GuildConfig> {
+        if let Some(config) = self.cache.lock().unwrap().get(&guild) {
+            return Ok(config.clone());
+        }
+
+        let config = sqlx::query_as!(
+            GuildSettingsRow,
+            "
+        SELECT prefix, disabled_commands, disabled_categories, countdown_channel, locale
+        FROM guild_settings
+        WHERE guild = ?
+            ",
+            guild
+        )
+        .fetch_optional(self.pool)
+        .await
+        .with_context(|| format!("failed to get settings for guild {guild}"))?
+        .map(GuildConfig::from)
+        .unwrap_or_default();
+
+        self.cache.lock().unwrap().insert(guild, config.clone());
+        Ok(config)
+    }
+
+    /// Saves the given guild's configuration.
+    pub async fn save(&self, guild: i64, config: &GuildConfig) -> Result<()> {
+        let disabled_commands = config.disabled_commands.join(" ");
+        let disabled_categories = config.disabled_categories.join(" ");
+
+        sqlx::query!(
+            "
+        INSERT INTO guild_settings
+            (guild, prefix, disabled_commands, disabled_categories, countdown_channel, locale)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (guild) DO UPDATE SET
+            prefix = excluded.prefix,
+            disabled_commands = excluded.disabled_commands,
+            disabled_categories = excluded.disabled_categories,
+            countdown_channel = excluded.countdown_channel,
+            locale = excluded.locale
+            ",
+            guild,
+            config.prefix,
+            disabled_commands,
+            disabled_categories,
+            config.countdown_channel,
+            config.locale
+        )
+        .execute(self.pool)
+        .await
+        .with_context(|| format!("failed to save settings for guild {guild}"))?;
+
+        self.cache.lock().unwrap().insert(guild, config.clone());
+        Ok(())
+    }
+
+    /// Puts all of the given guild's settings back to their defaults.
+    pub async fn reset(&self, guild: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM guild_settings WHERE guild = ?", guild)
+            .execute(self.pool)
+            .await
+            .with_context(|| format!("failed to reset settings for guild {guild}"))?;
+
+        self.cache.lock().unwrap().remove(&guild);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_prefixes() {
+        let mut config = GuildConfig::default();
+        assert_eq!(config.prefix(), DEFAULT_PREFIX);
+
+        config.set(Setting::Prefix, " !! ").unwrap();
+        assert_eq!(config.prefix(), "!!");
+        assert!(config.set(Setting::Prefix, "").is_err());
+        assert!(config.set(Setting::Prefix, "a b").is_err());
+        assert!(config.set(Setting::Prefix, "toolong").is_err());
+        assert_eq!(config.prefix(), "!!");
+
+        config.reset(Setting::Prefix);
+        assert_eq!(config.prefix(), DEFAULT_PREFIX);
+    }
+
+    #[test]
+    fn disables_commands_and_categories() {
+        let mut config = GuildConfig::default();
+        config
+            .set(Setting::DisabledCommands, "Roll, DIG  calc")
+            .unwrap();
+        config.set(Setting::DisabledCategories, "Mtg").unwrap();
+        assert_eq!(config.disabled_commands, ["roll", "dig", "calc"]);
+        assert_eq!(config.disabled_categories, ["mtg"]);
+
+        assert!(config.is_disabled("Dig", Some("General")));
+        assert!(config.is_disabled("mtg", Some("MTG")));
+        assert!(!config.is_disabled("weather", Some("General")));
+        assert!(!config.is_disabled("weather", None));
+
+        config.reset(Setting::DisabledCommands);
+        assert!(!config.is_disabled("dig", Some("General")));
+        assert!(config.is_disabled("mtg", Some("Mtg")));
+    }
+
+    #[test]
+    fn parses_channels_and_locales() {
+        let mut config = GuildConfig::default();
+        config
+            .set(Setting::CountdownChannel, "<#381880193700069377>")
+            .unwrap();
+        assert_eq!(config.countdown_channel, Some(381880193700069377));
+        assert!(config.set(Setting::CountdownChannel, "general").is_err());
+
+        config.set(Setting::Locale, "en_gb").unwrap();
+        assert_eq!(config.locale.as_deref(), Some("en-GB"));
+        config.set(Setting::Locale, "FR").unwrap();
+        assert_eq!(config.locale.as_deref(), Some("fr"));
+        assert!(config.set(Setting::Locale, "english").is_err());
+        assert!(config.set(Setting::Locale, "en-GB-x").is_err());
+
+        config.reset(Setting::CountdownChannel);
+        assert_eq!(config.countdown_channel, None);
+    }
+
+    #[test]
+    fn parses_setting_names() {
+        assert_eq!("PREFIX".parse::<Setting>().unwrap(), Setting::Prefix);
+        assert!("colour".parse::<Setting>().is_err());
+        for setting in Setting::ALL {
+            assert_eq!(setting.as_str().parse::<Setting>().unwrap(), setting);
+        }
+    }
+}
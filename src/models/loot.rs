@@ -0,0 +1,307 @@
+use anyhow::{bail, Context, Result};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The loot table used unless another one is configured.
+const DEFAULT_LOOT_TABLE: &str = include_str!("loot.json");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rarity::Common => "common",
+            Rarity::Uncommon => "uncommon",
+            Rarity::Rare => "rare",
+            Rarity::Epic => "epic",
+            Rarity::Legendary => "legendary",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LootItem {
+    pub id: String,
+    pub name: String,
+    pub emoji: String,
+    pub rarity: Rarity,
+    pub weight: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct LootTableDefinition {
+    cooldown_secs: u64,
+    items: Vec<LootItem>,
+}
+
+/// The things which can be dug up, and how likely each one is.
+#[derive(Debug)]
+pub struct LootTable {
+    items: Vec<LootItem>,
+    distribution: WeightedIndex<u32>,
+    cooldown: Duration,
+}
+
+impl LootTable {
+    /// Parses a loot table from its JSON definition.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let definition: LootTableDefinition =
+            serde_json::from_str(json).with_context(|| "failed to parse loot table")?;
+        if definition.items.is_empty() {
+            bail!("loot table has no items");
+        }
+        let mut ids = HashSet::new();
+        if let Some(item) = definition.items.iter().find(|item| !ids.insert(&item.id)) {
+            bail!("loot table has more than one item with id {}", item.id);
+        }
+
+        let distribution = WeightedIndex::new(definition.items.iter().map(|item| item.weight))
+            .with_context(|| "invalid loot table weights")?;
+
+        Ok(Self {
+            items: definition.items,
+            distribution,
+            cooldown: Duration::from_secs(definition.cooldown_secs),
+        })
+    }
+
+    pub fn builtin() -> Self {
+        Self::from_json(DEFAULT_LOOT_TABLE).expect("built-in loot table is invalid")
+    }
+
+    /// Picks an item at random, according to the items' weights.
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> &LootItem {
+        &self.items[self.distribution.sample(rng)]
+    }
+
+    pub fn item(&self, id: &str) -> Option<&LootItem> {
+        self.items.iter().find(|item| item.id == id)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InventoryEntry {
+    pub item: String,
+    pub quantity: i64,
+}
+
+pub enum DigOutcome<'a> {
+    /// Something was dug up; `quantity` is how many of it the user now has.
+    Found { item: &'a LootItem, quantity: i64 },
+    /// The user dug too recently, and can dig again after the given duration.
+    Cooldown(Duration),
+}
+
+/// Rolls the loot table for each dig and keeps track of what everyone has found.
+pub struct LootGame<'pool> {
+    pool: &'pool Pool<Sqlite>,
+    table: LootTable,
+    rng: Mutex<StdRng>,
+    last_dig: Mutex<HashMap<i64, Instant>>,
+}
+
+impl<'pool> LootGame<'pool> {
+    /// Creates a game using the given loot table.  Passing a seed makes the outcome of
+    /// every dig reproducible.
+    pub fn new(pool: &'pool Pool<Sqlite>, table: LootTable, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Self {
+            pool,
+            table,
+            rng: Mutex::new(rng),
+            last_dig: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn table(&self) -> &LootTable {
+        &self.table
+    }
+
+    /// Digs for the given user, unless they're still on cooldown.
+    pub async fn dig(&self, user_id: i64) -> Result<DigOutcome<'_>> {
+        let now = Instant::now();
+        {
+            let mut last_dig = self.last_dig.lock().unwrap();
+            // Anyone whose cooldown has passed no longer needs remembering.
+            last_dig.retain(|_, last| now - *last < self.table.cooldown);
+            if let Some(last) = last_dig.get(&user_id) {
+                return Ok(DigOutcome::Cooldown(self.table.cooldown - (now - *last)));
+            }
+            // Reserved before digging so that concurrent digs can't both get through.
+            last_dig.insert(user_id, now);
+        }
+
+        let item = self.table.roll(&mut *self.rng.lock().unwrap());
+        let quantity = match self.add_to_inventory(user_id, &item.id).await {
+            Ok(quantity) => quantity,
+            Err(why) => {
+                // Nothing was dug up, so the user shouldn't be on cooldown for it.
+                let mut last_dig = self.last_dig.lock().unwrap();
+                if last_dig.get(&user_id) == Some(&now) {
+                    last_dig.remove(&user_id);
+                }
+                return Err(why);
+            }
+        };
+
+        Ok(DigOutcome::Found { item, quantity })
+    }
+
+    /// Adds an item to the given user's inventory, returning how many of it they now have.
+    async fn add_to_inventory(&self, user_id: i64, item: &str) -> Result<i64> {
+        sqlx::query_scalar!(
+            "
+        INSERT INTO inventory (user_id, item, quantity) VALUES (?, ?, 1)
+        ON CONFLICT (user_id, item) DO UPDATE SET quantity = quantity + 1
+        RETURNING quantity
+            ",
+            user_id,
+            item
+        )
+        .fetch_one(self.pool)
+        .await
+        .with_context(|| format!("failed to add {item} to inventory of user {user_id}"))
+    }
+
+    /// Returns everything the given user has dug up.
+    pub async fn inventory(&self, user_id: i64) -> Result<Vec<InventoryEntry>> {
+        sqlx::query_as!(
+            InventoryEntry,
+            "SELECT item, quantity FROM inventory WHERE user_id = ? ORDER BY quantity DESC",
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| format!("failed to get inventory of user {user_id}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = r#"{
+        "cooldown_secs": 30,
+        "items": [
+            { "id": "pebble", "name": "Pebble", "emoji": "🪨", "rarity": "common", "weight": 9 },
+            { "id": "geode", "name": "Geode", "emoji": "💎", "rarity": "rare", "weight": 1 }
+        ]
+    }"#;
+
+    #[test]
+    fn rejects_invalid_tables() {
+        assert!(LootTable::from_json(r#"{ "cooldown_secs": 30, "items": [] }"#).is_err());
+        assert!(LootTable::from_json(&TABLE.replace(r#""weight": 9"#, r#""weight": 0"#)).is_ok());
+        let zero_weight = TABLE
+            .replace(r#""weight": 9"#, r#""weight": 0"#)
+            .replace(r#""weight": 1"#, r#""weight": 0"#);
+        assert!(LootTable::from_json(&zero_weight).is_err());
+        assert!(LootTable::from_json("not json").is_err());
+        let duplicate = TABLE.replace(r#""id": "geode""#, r#""id": "pebble""#);
+        assert!(LootTable::from_json(&duplicate).is_err());
+        LootTable::builtin();
+    }
+
+    #[test]
+    fn rolls_reproducibly() {
+        let table = LootTable::from_json(TABLE).unwrap();
+        let rolls = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..100)
+                .map(|_| table.roll(&mut rng).id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let first = rolls(42);
+        assert_eq!(first, rolls(42));
+        assert!(first.iter().any(|id| id == "pebble"));
+        assert!(first.iter().any(|id| id == "geode"));
+    }
+
+    #[tokio::test]
+    async fn digs_once_per_cooldown() {
+        let pool = crate::models::test_pool().await;
+        let game = LootGame::new(&pool, LootTable::from_json(TABLE).unwrap(), Some(42));
+
+        let DigOutcome::Found { item, quantity } = game.dig(1).await.unwrap() else {
+            panic!("the first dig should find something");
+        };
+        assert_eq!(quantity, 1);
+        let found = item.id.clone();
+
+        match game.dig(1).await.unwrap() {
+            DigOutcome::Cooldown(remaining) => {
+                assert!(remaining <= Duration::from_secs(30));
+                assert!(remaining > Duration::from_secs(29));
+            }
+            DigOutcome::Found { .. } => panic!("the second dig should be on cooldown"),
+        }
+
+        // Cooldowns are per user.
+        assert!(matches!(
+            game.dig(2).await.unwrap(),
+            DigOutcome::Found { .. }
+        ));
+
+        let inventory = game.inventory(1).await.unwrap();
+        assert_eq!(inventory.len(), 1);
+        assert_eq!(inventory[0].item, found);
+        assert_eq!(inventory[0].quantity, 1);
+    }
+
+    #[tokio::test]
+    async fn forgets_expired_cooldowns() {
+        let pool = crate::models::test_pool().await;
+        let table = TABLE.replace(r#""cooldown_secs": 30"#, r#""cooldown_secs": 0"#);
+        let game = LootGame::new(&pool, LootTable::from_json(&table).unwrap(), Some(42));
+
+        for user_id in 1..=3 {
+            assert!(matches!(
+                game.dig(user_id).await.unwrap(),
+                DigOutcome::Found { .. }
+            ));
+            assert_eq!(game.last_dig.lock().unwrap().len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_digs_dont_start_a_cooldown() {
+        let pool = crate::models::test_pool().await;
+        let game = LootGame::new(&pool, LootTable::from_json(TABLE).unwrap(), Some(42));
+
+        sqlx::query("ALTER TABLE inventory RENAME TO stash")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(game.dig(1).await.is_err());
+
+        sqlx::query("ALTER TABLE stash RENAME TO inventory")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(matches!(
+            game.dig(1).await.unwrap(),
+            DigOutcome::Found { .. }
+        ));
+    }
+}
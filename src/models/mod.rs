@@ -1,7 +1,32 @@
+pub mod achievements;
+pub mod calc;
 pub mod cards;
 pub mod countdowns;
+pub mod dice;
+pub mod guild_settings;
+pub mod loot;
+pub mod plots;
+pub mod preferences;
 pub mod probability;
 pub mod rocks;
 pub mod sandboxes;
+pub mod snippets;
+pub mod statistics;
 pub mod weather;
 pub mod zoo;
+
+/// Returns a pool for a fresh in-memory database with every migration applied.
+#[cfg(test)]
+pub(crate) async fn test_pool() -> sqlx::Pool<sqlx::Sqlite> {
+    // Every connection to `sqlite::memory:` gets its own database, so only allow one.
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("failed to open in-memory database");
+    sqlx::migrate!()
+        .run(&pool)
+        .await
+        .expect("failed to run migrations");
+    pool
+}
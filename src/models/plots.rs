@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder};
+use plotters::prelude::*;
+use std::iter;
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 360;
+const MARGIN: u32 = 20;
+/// How many points the curve is drawn through.
+const SAMPLES: usize = 400;
+
+const CURVE: RGBColor = RGBColor(40, 40, 40);
+const SHADE: RGBColor = RGBColor(66, 135, 245);
+
+/// Plots a probability density over `domain`, shading the area between the bounds of
+/// `shaded`, and returns it as a PNG.
+///
+/// No text is drawn, so that rendering doesn't depend on any fonts being installed.
+pub fn density_plot(
+    density: impl Fn(f64) -> f64,
+    domain: (f64, f64),
+    shaded: (f64, f64),
+) -> Result<Vec<u8>> {
+    let (start, end) = domain;
+    let lower = shaded.0.clamp(start, end);
+    let upper = shaded.1.clamp(start, end);
+
+    let points = (0..=SAMPLES)
+        .map(|i| {
+            let x = start + (end - start) * i as f64 / SAMPLES as f64;
+            (x, density(x))
+        })
+        .collect::<Vec<_>>();
+    let peak = points.iter().map(|(_, y)| *y).fold(0., f64::max);
+
+    let mut pixels = vec![0; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut pixels, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .margin(MARGIN)
+            .build_cartesian_2d(start..end, 0. ..peak * 1.1)?;
+
+        if lower < upper {
+            let area = iter::once((lower, density(lower)))
+                .chain(
+                    points
+                        .iter()
+                        .copied()
+                        .filter(|(x, _)| lower < *x && *x < upper),
+                )
+                .chain(iter::once((upper, density(upper))));
+            chart.draw_series(AreaSeries::new(area, 0., SHADE.mix(0.4)))?;
+            for bound in [lower, upper] {
+                chart.draw_series(LineSeries::new(
+                    [(bound, 0.), (bound, density(bound))],
+                    SHADE.stroke_width(2),
+                ))?;
+            }
+        }
+
+        chart.draw_series(LineSeries::new(points, CURVE.stroke_width(2)))?;
+        chart.draw_series(LineSeries::new([(start, 0.), (end, 0.)], CURVE))?;
+        root.present()?;
+    }
+
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(&pixels, WIDTH, HEIGHT, ColorType::Rgb8)
+        .with_context(|| "failed to encode plot")?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The reference plot.  Set `UPDATE_GOLDEN` when running the tests to regenerate it.
+    const GOLDEN: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/models/fixtures/normal_density.png"
+    );
+
+    #[test]
+    fn matches_golden_image() {
+        let standard_normal = |x: f64| (-x * x / 2.).exp() / (2. * std::f64::consts::PI).sqrt();
+        let png = density_plot(standard_normal, (-4., 4.), (-1., 1.)).unwrap();
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(GOLDEN, &png).unwrap();
+        }
+
+        // Compare the decoded pixels rather than the bytes, which depend on the encoder.
+        let actual = image::load_from_memory(&png).unwrap().to_rgb8();
+        let expected = image::open(GOLDEN).unwrap().to_rgb8();
+        assert_eq!(actual.dimensions(), expected.dimensions());
+        assert!(
+            actual.as_raw() == expected.as_raw(),
+            "plot differs from {GOLDEN}"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Context, Result};
+use sqlx::{Pool, Sqlite};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    Metric,
+    Imperial,
+    #[default]
+    Both,
+}
+
+impl Units {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Units::Metric => "metric",
+            Units::Imperial => "imperial",
+            Units::Both => "both",
+        }
+    }
+}
+
+impl FromStr for Units {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "metric" => Ok(Units::Metric),
+            "imperial" => Ok(Units::Imperial),
+            "both" => Ok(Units::Both),
+            _ => Err(anyhow!("unknown units {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserPreferences {
+    pub user_id: i64,
+    pub location_name: Option<String>,
+    pub lat: Option<String>,
+    pub lon: Option<String>,
+    pub units: String,
+    pub share_location: bool,
+}
+
+impl UserPreferences {
+    /// Returns the user's saved location as `(display_name, lat, lon)`, if they have one.
+    pub fn location(&self) -> Option<(String, String, String)> {
+        Some((
+            self.location_name.clone()?,
+            self.lat.clone()?,
+            self.lon.clone()?,
+        ))
+    }
+
+    pub fn units(&self) -> Units {
+        self.units.parse().unwrap_or_default()
+    }
+}
+
+pub struct PreferenceStore<'pool> {
+    pool: &'pool Pool<Sqlite>,
+}
+
+impl<'pool> PreferenceStore<'pool> {
+    pub fn new(pool: &'pool Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the preferences for the given user, if they have set any.
+    pub async fn get(&self, user_id: i64) -> Result<Option<UserPreferences>> {
+        sqlx::query_as!(
+            UserPreferences,
+            r#"
+        SELECT user_id, location_name, lat, lon, units, share_location as "share_location: bool"
+        FROM user_preferences
+        WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(self.pool)
+        .await
+        .with_context(|| format!("failed to get preferences for user {user_id}"))
+    }
+
+    /// Saves the given (already geocoded) location for the user.
+    pub async fn set_location(
+        &self,
+        user_id: i64,
+        location_name: &str,
+        lat: &str,
+        lon: &str,
+    ) -> Result<()> {
+        sqlx::query!(
+            "
+        INSERT INTO user_preferences (user_id, location_name, lat, lon)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (user_id) DO UPDATE
+        SET location_name = excluded.location_name, lat = excluded.lat, lon = excluded.lon
+            ",
+            user_id,
+            location_name,
+            lat,
+            lon
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to set location for user {user_id}"))
+    }
+
+    pub async fn set_units(&self, user_id: i64, units: Units) -> Result<()> {
+        let units = units.as_str();
+        sqlx::query!(
+            "
+        INSERT INTO user_preferences (user_id, units)
+        VALUES (?, ?)
+        ON CONFLICT (user_id) DO UPDATE SET units = excluded.units
+            ",
+            user_id,
+            units
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to set units for user {user_id}"))
+    }
+
+    /// Sets whether other users may look up the weather at this user's saved location.
+    pub async fn set_share_location(&self, user_id: i64, share_location: bool) -> Result<()> {
+        sqlx::query!(
+            "
+        INSERT INTO user_preferences (user_id, share_location)
+        VALUES (?, ?)
+        ON CONFLICT (user_id) DO UPDATE SET share_location = excluded.share_location
+            ",
+            user_id,
+            share_location
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to set location sharing for user {user_id}"))
+    }
+}
@@ -1,23 +1,451 @@
-use probability::distribution::{Distribution, Gaussian};
+use anyhow::{bail, Result};
+use probability::distribution::{
+    Beta, Binomial, Continuous, Discrete, Distribution, Exponential, Gamma, Gaussian, Inverse,
+};
+use std::fmt;
 
-#[derive(Clone, Debug, Default)]
-pub struct GaussianDist {
-    dist: Gaussian,
+/// χ²(k) is the same as a Gamma distribution with shape k/2 and scale 2.
+fn chi_squared_gamma(df: usize) -> Gamma {
+    Gamma::new(df as f64 / 2., 2.)
 }
 
-impl GaussianDist {
-    pub fn new() -> Self {
+/// A probability distribution, with parameters that have already been validated.
+#[derive(Clone, Copy, Debug)]
+pub enum Dist {
+    Normal { mu: f64, sigma: f64 },
+    Binomial { n: usize, p: f64 },
+    Poisson { lambda: f64 },
+    StudentT { df: f64 },
+    ChiSquared { df: usize },
+    Exponential { lambda: f64 },
+}
+
+impl Dist {
+    pub fn normal(mu: f64, sigma: f64) -> Result<Self> {
+        if !mu.is_finite() {
+            bail!("μ must be a finite number");
+        }
+        if !sigma.is_finite() || sigma <= 0. {
+            bail!("σ must be positive");
+        }
+        Ok(Dist::Normal { mu, sigma })
+    }
+
+    pub fn binomial(n: f64, p: f64) -> Result<Self> {
+        if n < 0. || n.fract() != 0. {
+            bail!("n must be a non-negative integer");
+        }
+        if !(0. ..=1.).contains(&p) {
+            bail!("p must be between 0 and 1");
+        }
+        Ok(Dist::Binomial { n: n as usize, p })
+    }
+
+    pub fn poisson(lambda: f64) -> Result<Self> {
+        if !lambda.is_finite() || lambda <= 0. {
+            bail!("λ must be positive");
+        }
+        Ok(Dist::Poisson { lambda })
+    }
+
+    pub fn student_t(df: f64) -> Result<Self> {
+        if !df.is_finite() || df <= 0. {
+            bail!("the degrees of freedom must be positive");
+        }
+        Ok(Dist::StudentT { df })
+    }
+
+    pub fn chi_squared(df: f64) -> Result<Self> {
+        if df < 1. || df.fract() != 0. {
+            bail!("the degrees of freedom must be a positive integer");
+        }
+        Ok(Dist::ChiSquared { df: df as usize })
+    }
+
+    pub fn exponential(lambda: f64) -> Result<Self> {
+        if !lambda.is_finite() || lambda <= 0. {
+            bail!("λ must be positive");
+        }
+        Ok(Dist::Exponential { lambda })
+    }
+
+    pub fn is_discrete(&self) -> bool {
+        matches!(self, Dist::Binomial { .. } | Dist::Poisson { .. })
+    }
+
+    /// Returns P(X <= x).
+    pub fn cdf(&self, x: f64) -> f64 {
+        match *self {
+            Dist::Normal { mu, sigma } => Gaussian::new(mu, sigma).distribution(x),
+            Dist::Binomial { n, p } => {
+                if x < 0. {
+                    0.
+                } else {
+                    Binomial::new(n, p).distribution(x.floor())
+                }
+            }
+            Dist::Poisson { lambda } => {
+                // P(X <= k) is the probability that a Gamma(k + 1, 1) variable exceeds λ.
+                if x < 0. {
+                    0.
+                } else {
+                    1. - Gamma::new(x.floor() + 1., 1.).distribution(lambda)
+                }
+            }
+            Dist::StudentT { df } => {
+                // The tails of Student's t are given by the regularised incomplete beta
+                // function, i.e. the CDF of a Beta(ν/2, 1/2) variable.
+                let tail = Beta::new(df / 2., 0.5, 0., 1.).distribution(df / (df + x * x)) / 2.;
+                if x < 0. {
+                    tail
+                } else {
+                    1. - tail
+                }
+            }
+            Dist::ChiSquared { df } => {
+                if x <= 0. {
+                    0.
+                } else {
+                    chi_squared_gamma(df).distribution(x)
+                }
+            }
+            Dist::Exponential { lambda } => {
+                if x <= 0. {
+                    0.
+                } else {
+                    Exponential::new(lambda).distribution(x)
+                }
+            }
+        }
+    }
+
+    /// Returns P(X = k) for discrete distributions.
+    pub fn pmf(&self, k: f64) -> Option<f64> {
+        if !self.is_discrete() {
+            return None;
+        }
+        if k < 0. || k.fract() != 0. {
+            return Some(0.);
+        }
+
+        Some(match *self {
+            Dist::Binomial { n, p } if k as usize <= n => Binomial::new(n, p).mass(k as usize),
+            Dist::Binomial { .. } => 0.,
+            _ => self.cdf(k) - self.cdf(k - 1.),
+        })
+    }
+
+    /// Returns the probability density at x, for the continuous distributions that have one
+    /// available.
+    pub fn density(&self, x: f64) -> Option<f64> {
+        match *self {
+            Dist::Normal { mu, sigma } => Some(Gaussian::new(mu, sigma).density(x)),
+            Dist::ChiSquared { df } => Some(if x <= 0. {
+                0.
+            } else {
+                chi_squared_gamma(df).density(x)
+            }),
+            Dist::Exponential { lambda } => Some(if x < 0. {
+                0.
+            } else {
+                Exponential::new(lambda).density(x)
+            }),
+            Dist::Binomial { .. } | Dist::Poisson { .. } | Dist::StudentT { .. } => None,
+        }
+    }
+
+    /// Returns P(lower <= X <= upper).
+    pub fn interval(&self, lower: f64, upper: f64) -> f64 {
+        if self.is_discrete() {
+            // For integer-valued distributions, P(X >= lower) includes X = ⌈lower⌉.
+            (self.cdf(upper) - self.cdf(lower.ceil() - 1.)).max(0.)
+        } else {
+            (self.cdf(upper) - self.cdf(lower)).max(0.)
+        }
+    }
+
+    /// Returns the x such that P(X <= x) = p, for continuous distributions.
+    pub fn inverse(&self, p: f64) -> Result<f64> {
+        if self.is_discrete() {
+            bail!("only continuous distributions can be inverted");
+        }
+        if !(0. ..=1.).contains(&p) {
+            bail!("p must be between 0 and 1");
+        }
+        if let Dist::Normal { mu, sigma } = *self {
+            return Ok(Gaussian::new(mu, sigma).inverse(p));
+        }
+        if p == 0. || p == 1. {
+            bail!("p must be strictly between 0 and 1");
+        }
+
+        // Find an interval containing x, then narrow it down by bisection.
+        let (mut lower, mut upper) = (-1., 1.);
+        while self.cdf(lower) > p {
+            lower *= 2.;
+        }
+        while self.cdf(upper) < p {
+            upper *= 2.;
+        }
+        loop {
+            let middle = (lower + upper) / 2.;
+            if middle <= lower || middle >= upper {
+                return Ok(middle);
+            }
+            if self.cdf(middle) < p {
+                lower = middle;
+            } else {
+                upper = middle;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Dist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dist::Normal { mu, sigma } => write!(f, "N({mu}, {sigma}²)"),
+            Dist::Binomial { n, p } => write!(f, "B({n}, {p})"),
+            Dist::Poisson { lambda } => write!(f, "Po({lambda})"),
+            Dist::StudentT { df } => write!(f, "t({df})"),
+            Dist::ChiSquared { df } => write!(f, "χ²({df})"),
+            Dist::Exponential { lambda } => write!(f, "Exp({lambda})"),
+        }
+    }
+}
+
+/// A distribution over a range of integers, given by its probability mass function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pmf {
+    /// The smallest value with a mass in `masses`.
+    min: i64,
+    masses: Vec<f64>,
+}
+
+impl Pmf {
+    /// The distribution which is always `value`.
+    pub fn constant(value: i64) -> Self {
+        Self {
+            min: value,
+            masses: vec![1.],
+        }
+    }
+
+    /// The distribution which is equally likely to be any value from `min` to `max`.
+    pub fn uniform(min: i64, max: i64) -> Self {
+        let len = (max - min + 1) as usize;
         Self {
-            dist: Gaussian::new(0., 1.),
+            min,
+            masses: vec![1. / len as f64; len],
+        }
+    }
+
+    /// Builds a distribution from `(value, mass)` pairs, which may repeat values.
+    pub fn from_masses(masses: impl IntoIterator<Item = (i64, f64)>) -> Self {
+        let masses = masses.into_iter().collect::<Vec<_>>();
+        let min = masses.iter().map(|(value, _)| *value).min().unwrap_or(0);
+        let max = masses.iter().map(|(value, _)| *value).max().unwrap_or(0);
+
+        let mut pmf = Self {
+            min,
+            masses: vec![0.; (max - min + 1) as usize],
+        };
+        for (value, mass) in masses {
+            pmf.masses[(value - min) as usize] += mass;
+        }
+        pmf
+    }
+
+    pub fn min(&self) -> i64 {
+        self.min
+    }
+
+    pub fn max(&self) -> i64 {
+        self.min + self.masses.len() as i64 - 1
+    }
+
+    /// Returns P(X = k).
+    pub fn mass(&self, k: i64) -> f64 {
+        usize::try_from(k - self.min)
+            .ok()
+            .and_then(|i| self.masses.get(i).copied())
+            .unwrap_or(0.)
+    }
+
+    /// Returns P(X <= k).
+    pub fn cdf(&self, k: i64) -> f64 {
+        (self.min..=k.min(self.max())).map(|k| self.mass(k)).sum()
+    }
+
+    /// Returns each value along with its mass, skipping impossible values.
+    pub fn masses(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        (self.min..)
+            .zip(self.masses.iter().copied())
+            .filter(|(_, mass)| *mass > 0.)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.masses().map(|(k, mass)| k as f64 * mass).sum()
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        self.masses()
+            .map(|(k, mass)| (k as f64 - mean).powi(2) * mass)
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// Returns the distribution of X + Y, where Y is independent of X.
+    pub fn add(&self, other: &Pmf) -> Pmf {
+        let mut masses = vec![0.; self.masses.len() + other.masses.len() - 1];
+        for (i, a) in self.masses.iter().enumerate() {
+            for (j, b) in other.masses.iter().enumerate() {
+                masses[i + j] += a * b;
+            }
+        }
+
+        Pmf {
+            min: self.min + other.min,
+            masses,
+        }
+    }
+
+    /// Returns the distribution of -X.
+    pub fn negate(&self) -> Pmf {
+        Pmf {
+            min: -self.max(),
+            masses: self.masses.iter().rev().copied().collect(),
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn cdfs_match_tables() {
+        let normal = Dist::normal(0., 1.).unwrap();
+        assert_close(normal.cdf(1.96), 0.9750, 1e-4);
+        assert_close(Dist::normal(100., 15.).unwrap().cdf(85.), 0.1587, 1e-4);
+
+        let t = Dist::student_t(10.).unwrap();
+        assert_close(t.cdf(2.228), 0.975, 1e-4);
+        assert_close(t.cdf(-2.228), 0.025, 1e-4);
+        assert_close(Dist::student_t(1.).unwrap().cdf(1.), 0.75, 1e-6);
+
+        let poisson = Dist::poisson(3.).unwrap();
+        assert_close(poisson.cdf(2.), 0.4232, 1e-4);
+        assert_close(poisson.cdf(2.5), 0.4232, 1e-4);
+        assert_eq!(poisson.cdf(-1.), 0.);
+
+        assert_close(Dist::chi_squared(1.).unwrap().cdf(3.841), 0.95, 1e-4);
+        assert_close(Dist::chi_squared(5.).unwrap().cdf(11.070), 0.95, 1e-4);
+        assert_close(Dist::binomial(10., 0.5).unwrap().cdf(5.), 0.6230, 1e-4);
+        assert_close(Dist::exponential(2.).unwrap().cdf(1.), 0.8647, 1e-4);
+    }
+
+    #[test]
+    fn masses_and_densities_match_tables() {
+        assert_close(
+            Dist::binomial(10., 0.5).unwrap().pmf(5.).unwrap(),
+            0.2461,
+            1e-4,
+        );
+        assert_close(Dist::poisson(3.).unwrap().pmf(3.).unwrap(), 0.2240, 1e-4);
+        assert_eq!(Dist::poisson(3.).unwrap().pmf(1.5), Some(0.));
+        assert_eq!(Dist::normal(0., 1.).unwrap().pmf(0.), None);
 
-    pub fn normal_cdf(&self, lower: f64, upper: f64) -> String {
-        let p = self.dist.distribution(upper) - self.dist.distribution(lower);
-        format!("P({lower} <= Z <= {upper}) = {p}")
+        assert_close(
+            Dist::normal(0., 1.).unwrap().density(0.).unwrap(),
+            0.3989,
+            1e-4,
+        );
+        assert_close(
+            Dist::chi_squared(2.).unwrap().density(2.).unwrap(),
+            0.1839,
+            1e-4,
+        );
+        assert_eq!(Dist::student_t(3.).unwrap().density(0.), None);
     }
 
-    pub fn normal_cdf_one_sided(&self, upper: f64) -> String {
-        format!("P(Z <= {upper}) = {}", self.dist.distribution(upper))
+    #[test]
+    fn intervals_include_both_ends_of_discrete_distributions() {
+        let binomial = Dist::binomial(10., 0.5).unwrap();
+        assert_close(binomial.interval(5., 5.), 0.2461, 1e-4);
+        assert_close(binomial.interval(4.5, 5.5), 0.2461, 1e-4);
+        assert_close(
+            Dist::normal(0., 1.).unwrap().interval(-1., 1.),
+            0.6827,
+            1e-4,
+        );
+    }
+
+    #[test]
+    fn inverses_match_tables() {
+        assert_close(
+            Dist::normal(0., 1.).unwrap().inverse(0.975).unwrap(),
+            1.96,
+            1e-3,
+        );
+        assert_close(
+            Dist::student_t(10.).unwrap().inverse(0.975).unwrap(),
+            2.228,
+            1e-3,
+        );
+        assert_close(
+            Dist::student_t(10.).unwrap().inverse(0.025).unwrap(),
+            -2.228,
+            1e-3,
+        );
+        assert_close(
+            Dist::chi_squared(2.).unwrap().inverse(0.95).unwrap(),
+            5.991,
+            1e-3,
+        );
+        assert_close(
+            Dist::exponential(2.).unwrap().inverse(0.5).unwrap(),
+            0.3466,
+            1e-4,
+        );
+
+        assert!(Dist::poisson(3.).unwrap().inverse(0.5).is_err());
+        assert!(Dist::student_t(10.).unwrap().inverse(1.).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        assert!(Dist::normal(f64::INFINITY, 1.).is_err());
+        assert!(Dist::normal(0., f64::INFINITY).is_err());
+        assert!(Dist::normal(0., 0.).is_err());
+        assert!(Dist::binomial(2.5, 0.5).is_err());
+        assert!(Dist::binomial(10., 1.5).is_err());
+        assert!(Dist::poisson(f64::NAN).is_err());
+        assert!(Dist::chi_squared(0.).is_err());
+        assert!(Dist::exponential(-1.).is_err());
+    }
+
+    #[test]
+    fn adds_independent_pmfs() {
+        let d6 = Pmf::uniform(1, 6);
+        let two_d6 = d6.add(&d6);
+
+        assert_eq!((two_d6.min(), two_d6.max()), (2, 12));
+        assert_close(two_d6.mass(7), 6. / 36., 1e-12);
+        assert_close(two_d6.cdf(4), 6. / 36., 1e-12);
+        assert_close(two_d6.mean(), 7., 1e-12);
+        assert_close(two_d6.std_dev(), (35f64 / 6.).sqrt(), 1e-12);
+
+        let negated = d6.negate();
+        assert_eq!((negated.min(), negated.max()), (-6, -1));
+        assert_close(d6.add(&negated).mean(), 0., 1e-12);
     }
 }
@@ -1,9 +1,63 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
 use sqlx::{Pool, Sqlite};
 
-struct RockCount {
-    user_id: i64,
-    count: i64,
+const DAY: i64 = 24 * 60 * 60;
+const WEEK: i64 = 7 * DAY;
+
+/// A period that a leaderboard can be limited to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Window {
+    Day,
+    Week,
+    Month,
+}
+
+impl Window {
+    pub fn seconds(self) -> i64 {
+        match self {
+            Window::Day => DAY,
+            Window::Week => WEEK,
+            Window::Month => 30 * DAY,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Window::Day => "in the last day",
+            Window::Week => "in the last week",
+            Window::Month => "in the last 30 days",
+        }
+    }
+}
+
+/// Which leaderboard to show, as asked for by `~dig top [global] [today|week|month]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LeaderboardQuery {
+    pub global: bool,
+    pub window: Option<Window>,
+}
+
+impl LeaderboardQuery {
+    /// Parses the arguments which follow `~dig top`.
+    pub fn parse(args: &str) -> Result<Self> {
+        let mut query = Self::default();
+        for arg in args.split_whitespace() {
+            match arg.to_lowercase().as_str() {
+                "global" => query.global = true,
+                "today" => query.window = Some(Window::Day),
+                "week" => query.window = Some(Window::Week),
+                "month" => query.window = Some(Window::Month),
+                _ => bail!("unknown leaderboard option {arg}"),
+            }
+        }
+        Ok(query)
+    }
+}
+
+pub struct RockCount {
+    pub user_id: i64,
+    pub count: i64,
 }
 
 pub struct RockCounter<'pool> {
@@ -15,39 +69,367 @@ impl<'pool> RockCounter<'pool> {
         Self { pool }
     }
 
-    /// Increments the rock count for the given user ID
-    pub async fn increment(&self, user_id: i64) -> Result<i64> {
-        // Make sure the user ID isn't too big for our DB
-        // let user_id =
-        //     i64::try_from(user_id).with_context(|| format!("user id {} too large", user_id))?;
+    /// Increments the rock count for the given user ID, along with their count in the guild
+    /// they dug in (if any), and logs the dig.  Returns the user's new global count.
+    pub async fn increment(&self, user_id: i64, guild: Option<i64>, channel: i64) -> Result<i64> {
+        let dug_at = Utc::now().timestamp();
 
-        // FIXME: surely there's a way to do this with a single statement?
-        //        my SQL-foo is weak.
+        // The transaction is deferred, so it only takes SQLite's write lock at its first
+        // statement.  That statement already writes, so the transaction never holds a read
+        // lock that it would later need to upgrade, which concurrent digs could deadlock on.
         let mut tx = self.pool.begin().await?;
 
-        let mut rock_count = sqlx::query_as!(
-            RockCount,
-            "SELECT user_id, count FROM rocks WHERE user_id = ?",
+        let count = sqlx::query_scalar!(
+            "
+        INSERT INTO rocks (user_id, count) VALUES (?, 1)
+        ON CONFLICT (user_id) DO UPDATE SET count = count + 1
+        RETURNING count
+            ",
             user_id
         )
-        .fetch_optional(&mut *tx)
+        .fetch_one(&mut *tx)
         .await
-        .with_context(|| format!("failed to get count for user {user_id}"))?
-        .unwrap_or(RockCount { user_id, count: 0 });
+        .with_context(|| format!("failed to update rock count for user {user_id}"))?;
 
-        rock_count.count += 1;
+        if let Some(guild) = guild {
+            sqlx::query!(
+                "
+            INSERT INTO guild_rocks (guild, user_id, count) VALUES (?, ?, 1)
+            ON CONFLICT (guild, user_id) DO UPDATE SET count = count + 1
+                ",
+                guild,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .with_context(|| {
+                format!("failed to update rock count for user {user_id} in guild {guild}")
+            })?;
+        }
 
         sqlx::query!(
-            "INSERT OR REPLACE INTO rocks (user_id, count) VALUES (?, ?)",
-            rock_count.user_id,
-            rock_count.count
+            "INSERT INTO dig_events (user_id, guild, channel, dug_at) VALUES (?, ?, ?, ?)",
+            user_id,
+            guild,
+            channel,
+            dug_at
         )
         .execute(&mut *tx)
         .await
-        .with_context(|| format!("failed to update rock count for user {user_id}"))?;
+        .with_context(|| format!("failed to log dig for user {user_id}"))?;
 
         tx.commit().await?;
 
-        Ok(rock_count.count)
+        Ok(count)
+    }
+
+    /// Returns the number of times the given user has dug, anywhere.
+    pub async fn count(&self, user_id: i64) -> Result<i64> {
+        sqlx::query_scalar!("SELECT count FROM rocks WHERE user_id = ?", user_id)
+            .fetch_optional(self.pool)
+            .await
+            .map(|count| count.unwrap_or(0))
+            .with_context(|| format!("failed to get count for user {user_id}"))
+    }
+
+    /// Returns the number of times the given user has dug in the given guild.
+    pub async fn guild_count(&self, guild: i64, user_id: i64) -> Result<i64> {
+        sqlx::query_scalar!(
+            "SELECT count FROM guild_rocks WHERE guild = ? AND user_id = ?",
+            guild,
+            user_id
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map(|count| count.unwrap_or(0))
+        .with_context(|| format!("failed to get count for user {user_id} in guild {guild}"))
+    }
+
+    /// Returns the global leaderboard position of someone who has dug `count` times.
+    pub async fn rank(&self, count: i64) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) + 1 AS "rank!: i64" FROM rocks WHERE count > ?"#,
+            count
+        )
+        .fetch_one(self.pool)
+        .await
+        .with_context(|| "failed to get rock rank")
+    }
+
+    /// Returns the leaderboard position in the given guild of someone who has dug there
+    /// `count` times.
+    pub async fn guild_rank(&self, guild: i64, count: i64) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) + 1 AS "rank!: i64" FROM guild_rocks WHERE guild = ? AND count > ?"#,
+            guild,
+            count
+        )
+        .fetch_one(self.pool)
+        .await
+        .with_context(|| format!("failed to get rock rank in guild {guild}"))
+    }
+
+    /// Returns the users who have dug the most, anywhere.
+    pub async fn top(&self, limit: i64) -> Result<Vec<RockCount>> {
+        sqlx::query_as!(
+            RockCount,
+            "SELECT user_id, count FROM rocks ORDER BY count DESC LIMIT ?",
+            limit
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| "failed to get rock leaderboard")
+    }
+
+    /// Returns the users who have dug the most in the given guild.
+    pub async fn guild_top(&self, guild: i64, limit: i64) -> Result<Vec<RockCount>> {
+        sqlx::query_as!(
+            RockCount,
+            "SELECT user_id, count FROM guild_rocks WHERE guild = ? ORDER BY count DESC LIMIT ?",
+            guild,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| format!("failed to get rock leaderboard for guild {guild}"))
+    }
+
+    /// Returns the users who have dug the most since the given UNIX timestamp, either in the
+    /// given guild or everywhere.
+    pub async fn top_since(
+        &self,
+        guild: Option<i64>,
+        since: i64,
+        limit: i64,
+    ) -> Result<Vec<RockCount>> {
+        sqlx::query_as!(
+            RockCount,
+            r#"
+        SELECT user_id, COUNT(*) AS "count!: i64"
+        FROM dig_events
+        WHERE dug_at >= ? AND (? IS NULL OR guild = ?)
+        GROUP BY user_id
+        ORDER BY 2 DESC
+        LIMIT ?
+            "#,
+            since,
+            guild,
+            guild,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| "failed to get rock leaderboard")
+    }
+
+    /// Returns the number of consecutive days (in UTC), up to and including today or
+    /// yesterday, on which the given user has dug.
+    pub async fn daily_streak(&self, user_id: i64) -> Result<i64> {
+        self.streak(user_id, DAY).await
+    }
+
+    /// Returns the number of consecutive weeks, up to and including this week or last week,
+    /// in which the given user has dug.
+    pub async fn weekly_streak(&self, user_id: i64) -> Result<i64> {
+        self.streak(user_id, WEEK).await
+    }
+
+    async fn streak(&self, user_id: i64, period: i64) -> Result<i64> {
+        let periods = sqlx::query_scalar!(
+            r#"
+        SELECT DISTINCT dug_at / ? AS "period!: i64"
+        FROM dig_events
+        WHERE user_id = ?
+        ORDER BY 1 DESC
+            "#,
+            period,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| format!("failed to get dig streak for user {user_id}"))?;
+
+        Ok(streak_length(&periods, Utc::now().timestamp() / period))
+    }
+}
+
+/// Counts the consecutive periods at the start of `periods` (which must be sorted newest
+/// first), provided the newest is the current or previous period.  The current period
+/// doesn't break a streak until it's over.
+fn streak_length(periods: &[i64], current: i64) -> i64 {
+    let Some(&latest) = periods.first() else {
+        return 0;
+    };
+    if latest < current - 1 {
+        return 0;
+    }
+
+    periods
+        .iter()
+        .enumerate()
+        .take_while(|&(i, &period)| period == latest - i as i64)
+        .count() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_pool;
+
+    const GUILD: i64 = 1;
+    const OTHER_GUILD: i64 = 2;
+
+    async fn dig(rock_counter: &RockCounter<'_>, user_id: i64, guild: Option<i64>, times: i64) {
+        for _ in 0..times {
+            rock_counter.increment(user_id, guild, 0).await.unwrap();
+        }
+    }
+
+    fn users(counts: &[RockCount]) -> Vec<(i64, i64)> {
+        counts
+            .iter()
+            .map(|rock_count| (rock_count.user_id, rock_count.count))
+            .collect()
+    }
+
+    #[test]
+    fn parses_leaderboard_queries() {
+        let parse = |args| LeaderboardQuery::parse(args).unwrap();
+
+        assert_eq!(parse(""), LeaderboardQuery::default());
+        assert_eq!(
+            parse("global"),
+            LeaderboardQuery {
+                global: true,
+                window: None
+            }
+        );
+        assert_eq!(
+            parse("week"),
+            LeaderboardQuery {
+                global: false,
+                window: Some(Window::Week)
+            }
+        );
+        assert_eq!(
+            parse("  Global   today "),
+            LeaderboardQuery {
+                global: true,
+                window: Some(Window::Day)
+            }
+        );
+        // The subcommand itself has already been consumed.
+        assert!(LeaderboardQuery::parse("top").is_err());
+        assert!(LeaderboardQuery::parse("global year").is_err());
+    }
+
+    #[tokio::test]
+    async fn counts_digs_globally_and_per_guild() {
+        let pool = test_pool().await;
+        let rock_counter = RockCounter::new(&pool);
+
+        dig(&rock_counter, 10, Some(GUILD), 3).await;
+        dig(&rock_counter, 10, Some(OTHER_GUILD), 2).await;
+        dig(&rock_counter, 10, None, 1).await;
+
+        assert_eq!(rock_counter.count(10).await.unwrap(), 6);
+        assert_eq!(rock_counter.guild_count(GUILD, 10).await.unwrap(), 3);
+        assert_eq!(rock_counter.guild_count(OTHER_GUILD, 10).await.unwrap(), 2);
+        assert_eq!(rock_counter.count(11).await.unwrap(), 0);
+        assert_eq!(rock_counter.guild_count(GUILD, 11).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn ties_share_a_rank() {
+        let pool = test_pool().await;
+        let rock_counter = RockCounter::new(&pool);
+
+        dig(&rock_counter, 10, Some(GUILD), 5).await;
+        dig(&rock_counter, 11, Some(GUILD), 3).await;
+        dig(&rock_counter, 12, Some(GUILD), 3).await;
+        dig(&rock_counter, 13, Some(OTHER_GUILD), 4).await;
+
+        assert_eq!(rock_counter.rank(5).await.unwrap(), 1);
+        assert_eq!(rock_counter.rank(4).await.unwrap(), 2);
+        assert_eq!(rock_counter.rank(3).await.unwrap(), 3);
+        assert_eq!(rock_counter.rank(1).await.unwrap(), 5);
+
+        // User 13 only dug in the other guild, so doesn't count here.
+        assert_eq!(rock_counter.guild_rank(GUILD, 5).await.unwrap(), 1);
+        assert_eq!(rock_counter.guild_rank(GUILD, 3).await.unwrap(), 2);
+        assert_eq!(rock_counter.guild_rank(OTHER_GUILD, 4).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn orders_leaderboards() {
+        let pool = test_pool().await;
+        let rock_counter = RockCounter::new(&pool);
+
+        dig(&rock_counter, 10, Some(GUILD), 1).await;
+        dig(&rock_counter, 11, Some(GUILD), 4).await;
+        dig(&rock_counter, 12, Some(OTHER_GUILD), 6).await;
+        dig(&rock_counter, 13, Some(GUILD), 2).await;
+        dig(&rock_counter, 13, Some(OTHER_GUILD), 3).await;
+
+        assert_eq!(
+            users(&rock_counter.top(10).await.unwrap()),
+            [(12, 6), (13, 5), (11, 4), (10, 1)]
+        );
+        assert_eq!(
+            users(&rock_counter.top(2).await.unwrap()),
+            [(12, 6), (13, 5)]
+        );
+        assert_eq!(
+            users(&rock_counter.guild_top(GUILD, 10).await.unwrap()),
+            [(11, 4), (13, 2), (10, 1)]
+        );
+        assert_eq!(
+            users(&rock_counter.guild_top(OTHER_GUILD, 10).await.unwrap()),
+            [(12, 6), (13, 3)]
+        );
+        assert!(rock_counter.guild_top(3, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn logs_digs_for_windowed_leaderboards() {
+        let pool = test_pool().await;
+        let rock_counter = RockCounter::new(&pool);
+
+        dig(&rock_counter, 10, Some(GUILD), 2).await;
+        dig(&rock_counter, 11, Some(OTHER_GUILD), 3).await;
+        dig(&rock_counter, 12, None, 1).await;
+
+        let now = Utc::now().timestamp();
+        assert_eq!(
+            users(&rock_counter.top_since(None, now - DAY, 10).await.unwrap()),
+            [(11, 3), (10, 2), (12, 1)]
+        );
+        assert_eq!(
+            users(
+                &rock_counter
+                    .top_since(Some(GUILD), now - DAY, 10)
+                    .await
+                    .unwrap()
+            ),
+            [(10, 2)]
+        );
+        assert!(rock_counter
+            .top_since(None, now + DAY, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(rock_counter.daily_streak(10).await.unwrap(), 1);
+        assert_eq!(rock_counter.weekly_streak(13).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn counts_consecutive_periods() {
+        assert_eq!(streak_length(&[], 100), 0);
+        assert_eq!(streak_length(&[100], 100), 1);
+        assert_eq!(streak_length(&[100, 99, 98, 96], 100), 3);
+        // Not having dug yet in the current period doesn't break the streak.
+        assert_eq!(streak_length(&[99, 98], 100), 2);
+        assert_eq!(streak_length(&[98, 97], 100), 0);
+        assert_eq!(streak_length(&[100, 98], 100), 1);
     }
 }
@@ -1,5 +1,121 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use code_sandbox::{CompletedSandbox, SandboxBuilder};
+use governor::clock::{Clock, DefaultClock};
+use std::fmt;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+mod replies;
+mod wrap;
+
+pub use replies::{ReplyCache, TrackedReply};
+pub use wrap::{wrap_python, wrap_rust};
+
+/// Extra time given to a sandbox, beyond its limits, to start up and shut down.
+const BACKSTOP_GRACE: Duration = Duration::from_secs(15);
+
+type UserRateLimiter = governor::DefaultKeyedRateLimiter<u64>;
+
+/// Resource limits applied to every sandbox we start.
+#[derive(Clone, Debug)]
+pub struct SandboxLimits {
+    pub memory_mb: u64,
+    pub cpus: f64,
+    /// How long the program may run for, after it has been compiled.
+    pub timeout: Duration,
+    /// How long compiling a Rust program may take.
+    pub compile_timeout: Duration,
+    pub network: bool,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            memory_mb: 256,
+            cpus: 1.0,
+            timeout: Duration::from_secs(10),
+            compile_timeout: Duration::from_secs(60),
+            network: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SandboxConfig {
+    pub limits: SandboxLimits,
+    /// Maximum number of sandboxes running at any one time.
+    pub max_concurrent: usize,
+    /// Maximum number of requests waiting for a free sandbox.
+    pub max_queued: usize,
+    /// Number of executions a single user may start per minute.
+    pub runs_per_minute: u32,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            limits: SandboxLimits::default(),
+            max_concurrent: 2,
+            max_queued: 8,
+            runs_per_minute: 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Python,
+    Rust,
+}
+
+impl Language {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Python => "python",
+            Language::Rust => "rust",
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "py" | "python" => Ok(Language::Python),
+            "rs" | "rust" => Ok(Language::Rust),
+            _ => Err(anyhow!("unknown language {s}")),
+        }
+    }
+}
+
+/// Reasons a sandbox request can be turned away before it runs.
+#[derive(Clone, Debug)]
+pub enum SandboxRejection {
+    RateLimited(Duration),
+    QueueFull,
+}
+
+impl fmt::Display for SandboxRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SandboxRejection::RateLimited(wait) => write!(
+                f,
+                "You're running code too quickly, try again in {}s.",
+                wait.as_secs().max(1)
+            ),
+            SandboxRejection::QueueFull => {
+                write!(
+                    f,
+                    "Too many sandboxes are running right now, try again later."
+                )
+            }
+        }
+    }
+}
 
 fn format_output(sandbox: CompletedSandbox) -> String {
     let mut reply = sandbox.stdout().trim_end();
@@ -11,28 +127,219 @@ fn format_output(sandbox: CompletedSandbox) -> String {
     reply.to_string()
 }
 
-async fn _run_python_code(code: String) -> Result<String> {
+/// Builds a shell command which kills `command` if it runs for longer than `timeout`, saying
+/// so in the output.  Killing it from inside the sandbox stops the container, which
+/// dropping the future wouldn't.
+fn with_timeout(command: &str, timeout: Duration, stage: &str) -> String {
+    let secs = timeout.as_secs().max(1);
+    format!(
+        "timeout -k 1 {secs} {command} || {{ status=$?; \
+         [ $status -eq 124 ] && echo '{stage} timed out after {secs}s'; exit $status; }}"
+    )
+}
+
+fn apply_limits(builder: &mut SandboxBuilder, limits: &SandboxLimits) {
+    builder.memory_limit(limits.memory_mb * 1024 * 1024);
+    builder.cpu_limit(limits.cpus);
+    builder.network(limits.network);
+}
+
+async fn _run_python_code(code: String, limits: &SandboxLimits) -> Result<String> {
     let mut builder = SandboxBuilder::new("dcchut/code-sandbox-python")?;
-    builder.entry_point(["python3", "/playground/src/main.py"]);
+    let command = with_timeout(
+        "python3 /playground/src/main.py",
+        limits.timeout,
+        "Execution",
+    );
+    builder.entry_point(["sh", "-c", command.as_str()]);
     builder.mount("/playground/src/main.py", code)?;
+    apply_limits(&mut builder, limits);
 
     let sandbox = builder.build()?;
     Ok(format_output(sandbox.execute().await?))
 }
 
-pub async fn run_python_code<S: ToString>(code: S) -> Result<String> {
-    _run_python_code(code.to_string()).await
+pub async fn run_python_code<S: ToString>(code: S, limits: &SandboxLimits) -> Result<String> {
+    _run_python_code(code.to_string(), limits).await
 }
 
-async fn _run_rust_code(code: String) -> Result<String> {
+async fn _run_rust_code(code: String, limits: &SandboxLimits) -> Result<String> {
     let mut builder = SandboxBuilder::new("dcchut/code-sandbox-rust-stable")?;
-    builder.entry_point(["cargo", "run", "--release"]);
+    let command = format!(
+        "{} && {}",
+        with_timeout(
+            "cargo build --release --quiet",
+            limits.compile_timeout,
+            "Compilation"
+        ),
+        with_timeout("cargo run --release --quiet", limits.timeout, "Execution")
+    );
+    builder.entry_point(["sh", "-c", command.as_str()]);
     builder.mount("/playground/src/main.rs", code)?;
+    apply_limits(&mut builder, limits);
 
     let sandbox = builder.build()?;
     Ok(format_output(sandbox.execute().await?))
 }
 
-pub async fn run_rust_code<S: ToString>(code: S) -> Result<String> {
-    _run_rust_code(code.to_string()).await
+pub async fn run_rust_code<S: ToString>(code: S, limits: &SandboxLimits) -> Result<String> {
+    _run_rust_code(code.to_string(), limits).await
+}
+
+/// Gatekeeper for sandbox executions: enforces a per-user rate limit and caps the
+/// number of sandboxes running (and waiting to run) at once.
+pub struct SandboxRunner {
+    config: SandboxConfig,
+    semaphore: Semaphore,
+    limiter: UserRateLimiter,
+    outstanding: AtomicUsize,
+}
+
+impl SandboxRunner {
+    pub fn new(config: SandboxConfig) -> Self {
+        let runs_per_minute = NonZeroU32::new(config.runs_per_minute).unwrap_or(NonZeroU32::MIN);
+
+        Self {
+            semaphore: Semaphore::new(config.max_concurrent.max(1)),
+            limiter: governor::RateLimiter::keyed(governor::Quota::per_minute(runs_per_minute)),
+            outstanding: AtomicUsize::new(0),
+            config,
+        }
+    }
+
+    /// Reserves a place in the sandbox queue for the given user.
+    pub fn enqueue(&self, user_id: u64) -> Result<SandboxTicket<'_>, SandboxRejection> {
+        let max_concurrent = self.config.max_concurrent.max(1);
+        let ahead = self.outstanding.fetch_add(1, Ordering::SeqCst);
+        let ticket = SandboxTicket {
+            runner: self,
+            position: (ahead + 1).saturating_sub(max_concurrent),
+        };
+
+        if ticket.position > self.config.max_queued {
+            return Err(SandboxRejection::QueueFull);
+        }
+
+        // Only checked once there's room in the queue, so a rejected request doesn't use
+        // up any of the user's quota.
+        if let Err(not_until) = self.limiter.check_key(&user_id) {
+            let wait = not_until.wait_time_from(DefaultClock::default().now());
+            return Err(SandboxRejection::RateLimited(wait));
+        }
+
+        Ok(ticket)
+    }
+}
+
+/// A reserved place in the sandbox queue.
+pub struct SandboxTicket<'a> {
+    runner: &'a SandboxRunner,
+    position: usize,
+}
+
+impl<'a> SandboxTicket<'a> {
+    /// Returns the number of requests ahead of this one, or zero if it can run immediately.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Waits for a free sandbox and then executes the given code in it.
+    pub async fn run(self, language: Language, code: String) -> Result<String> {
+        let _permit = self.runner.semaphore.acquire().await?;
+        let limits = &self.runner.config.limits;
+
+        // The sandbox enforces its own time limits, so this is only a backstop in case it
+        // doesn't stop.
+        let backstop = limits.compile_timeout + limits.timeout + BACKSTOP_GRACE;
+        let execution = async {
+            match language {
+                Language::Python => run_python_code(code, limits).await,
+                Language::Rust => run_rust_code(code, limits).await,
+            }
+        };
+
+        match tokio::time::timeout(backstop, execution).await {
+            Ok(result) => result,
+            Err(_) => Ok(format!("Execution timed out after {}s", backstop.as_secs())),
+        }
+    }
+}
+
+impl<'a> Drop for SandboxTicket<'a> {
+    fn drop(&mut self) {
+        self.runner.outstanding.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runner(max_concurrent: usize, max_queued: usize, runs_per_minute: u32) -> SandboxRunner {
+        SandboxRunner::new(SandboxConfig {
+            max_concurrent,
+            max_queued,
+            runs_per_minute,
+            ..SandboxConfig::default()
+        })
+    }
+
+    fn outstanding(runner: &SandboxRunner) -> usize {
+        runner.outstanding.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn queues_beyond_the_running_sandboxes() {
+        let runner = runner(2, 2, 100);
+        let tickets = (1..=4)
+            .map(|user_id| runner.enqueue(user_id).ok().unwrap())
+            .collect::<Vec<_>>();
+        let positions = tickets
+            .iter()
+            .map(SandboxTicket::position)
+            .collect::<Vec<_>>();
+        assert_eq!(positions, [0, 0, 1, 2]);
+
+        assert!(matches!(
+            runner.enqueue(5),
+            Err(SandboxRejection::QueueFull)
+        ));
+        assert_eq!(outstanding(&runner), 4);
+
+        drop(tickets);
+        assert_eq!(outstanding(&runner), 0);
+        assert_eq!(runner.enqueue(5).ok().unwrap().position(), 0);
+        assert_eq!(outstanding(&runner), 0);
+    }
+
+    #[test]
+    fn rate_limits_each_user() {
+        let runner = runner(2, 8, 2);
+        assert!(runner.enqueue(1).is_ok());
+        assert!(runner.enqueue(1).is_ok());
+        match runner.enqueue(1) {
+            Err(SandboxRejection::RateLimited(wait)) => assert!(wait > Duration::ZERO),
+            _ => panic!("expected the third run to be rate limited"),
+        }
+        assert_eq!(outstanding(&runner), 0);
+
+        assert!(runner.enqueue(2).is_ok());
+    }
+
+    #[test]
+    fn full_queues_dont_use_up_quota() {
+        let runner = runner(1, 0, 1);
+        let ticket = runner.enqueue(1).ok().unwrap();
+        for _ in 0..3 {
+            assert!(matches!(
+                runner.enqueue(2),
+                Err(SandboxRejection::QueueFull)
+            ));
+        }
+        assert_eq!(outstanding(&runner), 1);
+
+        drop(ticket);
+        assert!(runner.enqueue(2).is_ok());
+        assert_eq!(outstanding(&runner), 0);
+    }
 }
@@ -0,0 +1,167 @@
+use crate::models::sandboxes::Language;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The bot's reply to a sandbox invocation, along with how the code was run.
+#[derive(Clone, Copy, Debug)]
+pub struct TrackedReply {
+    pub language: Language,
+    pub raw: bool,
+    pub channel_id: u64,
+    pub reply_id: u64,
+}
+
+impl TrackedReply {
+    /// The name of the command which produced this reply.
+    pub fn command(&self) -> &'static str {
+        match (self.language, self.raw) {
+            (Language::Rust, false) => "rust",
+            (Language::Rust, true) => "rust_raw",
+            (Language::Python, false) => "py",
+            (Language::Python, true) => "py_raw",
+        }
+    }
+
+    /// Returns the code from an edited invocation, or `None` if the message no longer
+    /// invokes the same command with the given prefix.
+    pub fn code_from<'a>(&self, content: &'a str, prefix: &str) -> Option<&'a str> {
+        let rest = content.trim_start().strip_prefix(prefix)?;
+        let (command, code) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        (command == self.command()).then_some(code)
+    }
+}
+
+struct Entries {
+    replies: HashMap<u64, (Instant, TrackedReply)>,
+    order: VecDeque<(u64, Instant)>,
+}
+
+/// Bounded, expiring map from a triggering message ID to the bot's reply.
+pub struct ReplyCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+}
+
+impl ReplyCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Mutex::new(Entries {
+                replies: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Drops entries which have expired, then the oldest entries until at most `max_len`
+    /// remain.
+    fn evict(&self, entries: &mut Entries, now: Instant, max_len: usize) {
+        while let Some(&(source_id, inserted)) = entries.order.front() {
+            let expired = now.duration_since(inserted) >= self.ttl;
+            if !expired && entries.replies.len() <= max_len {
+                break;
+            }
+
+            entries.order.pop_front();
+            if matches!(entries.replies.get(&source_id), Some((at, _)) if *at == inserted) {
+                entries.replies.remove(&source_id);
+            }
+        }
+    }
+
+    pub fn insert(&self, source_id: u64, reply: TrackedReply) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("reply cache poisoned");
+
+        // Make room for the new entry.
+        self.evict(&mut entries, now, self.capacity.saturating_sub(1));
+        entries.replies.insert(source_id, (now, reply));
+        entries.order.push_back((source_id, now));
+    }
+
+    pub fn get(&self, source_id: u64) -> Option<TrackedReply> {
+        let mut entries = self.entries.lock().expect("reply cache poisoned");
+
+        self.evict(&mut entries, Instant::now(), self.capacity);
+        entries.replies.get(&source_id).map(|(_, reply)| *reply)
+    }
+
+    pub fn remove(&self, source_id: u64) -> Option<TrackedReply> {
+        let mut entries = self.entries.lock().expect("reply cache poisoned");
+
+        self.evict(&mut entries, Instant::now(), self.capacity);
+        entries.replies.remove(&source_id).map(|(_, reply)| reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(reply_id: u64) -> TrackedReply {
+        TrackedReply {
+            language: Language::Rust,
+            raw: false,
+            channel_id: 1,
+            reply_id,
+        }
+    }
+
+    #[test]
+    fn evicts_the_oldest_entries() {
+        let cache = ReplyCache::new(2, Duration::from_secs(60 * 60));
+        cache.insert(1, reply(10));
+        cache.insert(2, reply(20));
+        cache.insert(3, reply(30));
+
+        assert!(cache.get(1).is_none());
+        assert_eq!(cache.get(2).map(|reply| reply.reply_id), Some(20));
+        assert_eq!(cache.get(3).map(|reply| reply.reply_id), Some(30));
+
+        // Replacing an entry doesn't let its old position evict it.
+        cache.insert(2, reply(21));
+        cache.insert(4, reply(40));
+        assert!(cache.get(3).is_none());
+        assert_eq!(cache.get(2).map(|reply| reply.reply_id), Some(21));
+        assert_eq!(cache.get(4).map(|reply| reply.reply_id), Some(40));
+
+        assert_eq!(cache.remove(4).map(|reply| reply.reply_id), Some(40));
+        assert!(cache.get(4).is_none());
+    }
+
+    #[test]
+    fn expires_entries() {
+        let cache = ReplyCache::new(10, Duration::ZERO);
+        cache.insert(1, reply(10));
+        assert!(cache.get(1).is_none());
+        assert!(cache.remove(1).is_none());
+    }
+
+    #[test]
+    fn extracts_code_from_the_same_command() {
+        let rust = reply(10);
+        let py_raw = TrackedReply {
+            language: Language::Python,
+            raw: true,
+            ..rust
+        };
+
+        assert_eq!(rust.code_from("~rust 1 + 1", "~"), Some("1 + 1"));
+        assert_eq!(
+            rust.code_from("  ~rust\nlet x = 1;", "~"),
+            Some("let x = 1;")
+        );
+        assert_eq!(rust.code_from("~rust", "~"), Some(""));
+        assert_eq!(rust.code_from("!rust 1", "!"), Some("1"));
+        assert_eq!(py_raw.code_from("~py_raw print(1)", "~"), Some("print(1)"));
+
+        assert_eq!(rust.code_from("~py 1 + 1", "~"), None);
+        assert_eq!(rust.code_from("~rust_raw fn main() {}", "~"), None);
+        assert_eq!(rust.code_from("!rust 1", "~"), None);
+        assert_eq!(rust.code_from("never mind", "~"), None);
+        assert_eq!(py_raw.code_from("~py print(1)", "~"), None);
+    }
+}
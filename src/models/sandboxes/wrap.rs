@@ -0,0 +1,350 @@
+//! Turns the snippets given to `~rust` and `~py` into complete programs which print the
+//! value of their final expression.
+
+/// Helper appended to every wrapped Rust program: debug-prints a value unless it is `()`.
+const RUST_PRINT_RESULT: &str = r#"fn __robbot_print_result<T: std::fmt::Debug>(result: T) {
+    if std::any::type_name::<T>() != "()" {
+        println!("{result:?}");
+    }
+}"#;
+
+/// Python driver which runs every statement in `__robbot_src` and then evaluates (and
+/// prints) a trailing expression, like the interactive interpreter does.
+const PYTHON_DRIVER: &str = r#"import ast as __robbot_ast
+
+__robbot_tree = __robbot_ast.parse(__robbot_src, "<input>", "exec")
+__robbot_last = None
+if __robbot_tree.body and isinstance(__robbot_tree.body[-1], __robbot_ast.Expr):
+    __robbot_last = __robbot_ast.Expression(__robbot_tree.body.pop().value)
+
+exec(compile(__robbot_tree, "<input>", "exec"))
+if __robbot_last is not None:
+    __robbot_value = eval(compile(__robbot_last, "<input>", "eval"))
+    if __robbot_value is not None:
+        print(repr(__robbot_value))
+"#;
+
+#[derive(Clone)]
+struct Cursor<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Consumes an identifier (possibly empty), returning it.
+    fn ident(&mut self) -> &'a str {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_') {
+            self.bump();
+        }
+        &self.src[start..self.pos]
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                self.skip_block_comment();
+            } else if matches!(self.peek(), Some(c) if c.is_whitespace()) {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn skip_block_comment(&mut self) {
+        let mut depth = 0;
+        while !self.rest().is_empty() {
+            if self.rest().starts_with("/*") {
+                depth += 1;
+                self.pos += 2;
+            } else if self.rest().starts_with("*/") {
+                depth -= 1;
+                self.pos += 2;
+                if depth == 0 {
+                    return;
+                }
+            } else {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skips a (possibly byte) string literal whose opening quote is next.
+    fn skip_string(&mut self) {
+        self.bump();
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => {
+                    self.bump();
+                }
+                '"' => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips a raw string literal, positioned just after its `r`.
+    fn skip_raw_string(&mut self) {
+        let mut hashes = 0;
+        while self.peek() == Some('#') {
+            hashes += 1;
+            self.bump();
+        }
+        self.bump();
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        match self.rest().find(&terminator) {
+            Some(offset) => self.pos += offset + terminator.len(),
+            None => self.pos = self.src.len(),
+        }
+    }
+
+    /// Skips a character literal or lifetime, positioned on its opening quote.
+    fn skip_quote(&mut self) {
+        self.bump();
+        match self.bump() {
+            Some('\\') => {
+                self.bump();
+                while let Some(c) = self.bump() {
+                    if c == '\'' {
+                        break;
+                    }
+                }
+            }
+            Some(_) if self.peek() == Some('\'') => {
+                self.bump();
+            }
+            _ => {
+                self.ident();
+            }
+        }
+    }
+
+    /// Advances past the next token, returning its first character.  Literals, comments
+    /// and identifiers are skipped as a whole so that any delimiters within them are ignored.
+    fn next_token(&mut self) -> Option<char> {
+        self.skip_trivia();
+        let c = self.peek()?;
+
+        if c == '"' {
+            self.skip_string();
+        } else if c == '\'' {
+            self.skip_quote();
+        } else if c.is_alphabetic() || c == '_' {
+            let ident = self.ident();
+            match (ident, self.peek()) {
+                ("r" | "br", Some('"' | '#')) => self.skip_raw_string(),
+                ("b", Some('"')) => self.skip_string(),
+                ("b", Some('\'')) => self.skip_quote(),
+                _ => {}
+            }
+        } else {
+            self.bump();
+        }
+
+        Some(c)
+    }
+
+    /// Skips a balanced group of delimiters, positioned on the opening delimiter.
+    fn skip_group(&mut self) {
+        let mut depth = 0usize;
+        while let Some(c) = self.next_token() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips to the end of the current item: a semicolon at the top level, or (unless
+    /// `semicolon_only` is set) the closing brace of its body.
+    fn skip_item(&mut self, semicolon_only: bool) {
+        let mut depth = 0usize;
+        while let Some(c) = self.next_token() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                    if c == '}' && depth == 0 && !semicolon_only {
+                        return;
+                    }
+                }
+                ';' if depth == 0 => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Attempts to skip a single item (`use`, `fn`, `struct`, ...), including any attributes
+    /// and visibility.  Returns false, without advancing, if no item starts here.
+    fn skip_leading_item(&mut self) -> bool {
+        let mut probe = self.clone();
+        probe.skip_trivia();
+
+        while probe.peek() == Some('#') {
+            probe.bump();
+            if probe.peek() == Some('!') {
+                probe.bump();
+            }
+            if probe.peek() != Some('[') {
+                return false;
+            }
+            probe.skip_group();
+            probe.skip_trivia();
+        }
+
+        let mut keyword = probe.ident();
+        if keyword == "pub" {
+            probe.skip_trivia();
+            if probe.peek() == Some('(') {
+                probe.skip_group();
+            }
+            probe.skip_trivia();
+            keyword = probe.ident();
+        }
+
+        // `async`, `unsafe` and `const` also begin blocks and expressions, so only treat
+        // them as items when followed by a suitable keyword.
+        let mut semicolon_only = false;
+        if matches!(keyword, "async" | "unsafe" | "const") {
+            let mut lookahead = probe.clone();
+            lookahead.skip_trivia();
+            match (keyword, lookahead.ident()) {
+                (_, "fn" | "impl" | "trait") => {}
+                ("const", "") => return false,
+                ("const", _) => semicolon_only = true,
+                _ => return false,
+            }
+        } else {
+            match keyword {
+                "use" | "static" | "type" => semicolon_only = true,
+                "fn" | "struct" | "enum" | "impl" | "mod" | "trait" | "extern" | "macro_rules" => {}
+                _ => return false,
+            }
+        }
+
+        probe.skip_item(semicolon_only);
+        *self = probe;
+        true
+    }
+}
+
+/// Splits a Rust snippet into its leading items and the remaining body.
+fn split_leading_items(src: &str) -> (&str, &str) {
+    let mut cursor = Cursor::new(src);
+    while cursor.skip_leading_item() {}
+
+    src.split_at(cursor.pos)
+}
+
+/// Wraps a Rust snippet into a program: leading items are hoisted to the top level and the
+/// remainder becomes the body of `main`, whose final expression (if any) is debug-printed.
+pub fn wrap_rust(src: &str) -> String {
+    let (items, body) = split_leading_items(src);
+
+    format!(
+        "{items}\n\nfn main() {{\n    let result = {{\n{body}\n    }};\n    __robbot_print_result(result);\n}}\n\n{RUST_PRINT_RESULT}\n"
+    )
+}
+
+/// Wraps a Python snippet so that its final expression (if any) is printed REPL-style.
+pub fn wrap_python(src: &str) -> String {
+    // A JSON string is also a valid Python string literal.
+    let literal = serde_json::to_string(src).expect("failed to encode python source");
+
+    format!("__robbot_src = {literal}\n{PYTHON_DRIVER}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hoists_leading_items() {
+        let src = "use std::collections::HashMap;\n\
+                   #[derive(Debug)]\n\
+                   struct Point { x: i32 }\n\
+                   impl Point { fn new() -> Self { Point { x: 1 } } }\n\
+                   mod inner { pub fn f() {} }\n\
+                   pub(crate) fn double(x: i32) -> i32 { x * 2 }\n\
+                   double(Point::new().x)";
+        let (items, body) = split_leading_items(src);
+
+        assert!(items.ends_with("pub(crate) fn double(x: i32) -> i32 { x * 2 }"));
+        assert_eq!(body.trim(), "double(Point::new().x)");
+    }
+
+    #[test]
+    fn leaves_statements_in_main() {
+        let (items, body) = split_leading_items("let x = 5;\nfn f() {}\nx");
+        assert_eq!(items, "");
+        assert_eq!(body, "let x = 5;\nfn f() {}\nx");
+
+        let (items, body) = split_leading_items("const fn one() -> i32 { 1 }\nasync { 1 };");
+        assert_eq!(items, "const fn one() -> i32 { 1 }");
+        assert_eq!(body.trim(), "async { 1 };");
+    }
+
+    #[test]
+    fn ignores_delimiters_in_literals_and_comments() {
+        let src = "fn a() -> &'static str { \"} fn b() {\" }\n\
+                   fn c() -> &'static str { r#\"\"} fn d\"# }\n\
+                   fn e() -> char { '}' } // }\n\
+                   /* fn f() { */ fn g() {}\n\
+                   a()";
+        let (items, body) = split_leading_items(src);
+
+        assert!(items.ends_with("fn g() {}"));
+        assert_eq!(body.trim(), "a()");
+    }
+
+    #[test]
+    fn prints_trailing_expressions_only() {
+        // A trailing statement leaves the block's value as `()`, which isn't printed.
+        let program = wrap_rust("let x = 1;");
+        assert!(program.contains("let result = {\nlet x = 1;\n    };"));
+        assert!(program.contains("if std::any::type_name::<T>() != \"()\""));
+
+        let program = wrap_rust("fn f() -> i32 { 2 }\nf() + 1");
+        assert!(program.starts_with("fn f() -> i32 { 2 }\n\nfn main() {"));
+        assert!(program.contains("let result = {\n\nf() + 1\n    };"));
+    }
+
+    #[test]
+    fn embeds_python_source_as_a_literal() {
+        let program = wrap_python("def f(x):\n    return \"x\" * x\n\nf(3)");
+
+        assert!(program
+            .starts_with("__robbot_src = \"def f(x):\\n    return \\\"x\\\" * x\\n\\nf(3)\"\n"));
+        assert!(program.ends_with(PYTHON_DRIVER));
+    }
+}
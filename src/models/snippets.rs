@@ -0,0 +1,101 @@
+use crate::models::sandboxes::Language;
+use anyhow::{Context, Result};
+use sqlx::{Pool, Sqlite};
+
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub name: String,
+    pub language: String,
+    pub code: String,
+}
+
+impl Snippet {
+    pub fn language(&self) -> Result<Language> {
+        self.language.parse()
+    }
+}
+
+pub struct SnippetStore<'pool> {
+    pool: &'pool Pool<Sqlite>,
+}
+
+impl<'pool> SnippetStore<'pool> {
+    pub fn new(pool: &'pool Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    /// Saves a snippet for the given user, replacing any existing snippet with the same name.
+    pub async fn save(
+        &self,
+        user_id: i64,
+        name: &str,
+        language: Language,
+        code: &str,
+    ) -> Result<()> {
+        let language = language.as_str();
+        sqlx::query!(
+            "
+        INSERT INTO snippets (user_id, name, language, code)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (user_id, name) DO UPDATE SET language = excluded.language, code = excluded.code
+            ",
+            user_id,
+            name,
+            language,
+            code
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to save snippet {name} for user {user_id}"))
+    }
+
+    /// Returns (if it exists) the user's snippet with the given name.
+    pub async fn get(&self, user_id: i64, name: &str) -> Result<Option<Snippet>> {
+        sqlx::query_as!(
+            Snippet,
+            "SELECT name, language, code FROM snippets WHERE user_id = ? AND name = ?",
+            user_id,
+            name
+        )
+        .fetch_optional(self.pool)
+        .await
+        .with_context(|| format!("failed to get snippet {name} for user {user_id}"))
+    }
+
+    /// Returns all of the user's snippets, ordered by name.
+    pub async fn list(&self, user_id: i64) -> Result<Vec<Snippet>> {
+        sqlx::query_as!(
+            Snippet,
+            "SELECT name, language, code FROM snippets WHERE user_id = ? ORDER BY name ASC",
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| format!("failed to list snippets for user {user_id}"))
+    }
+
+    /// Copies a snippet into another user's library.  Returns false if the recipient
+    /// already has a snippet with that name.
+    pub async fn share(&self, snippet: &Snippet, recipient_id: i64) -> Result<bool> {
+        sqlx::query!(
+            "
+        INSERT OR IGNORE INTO snippets (user_id, name, language, code)
+        VALUES (?, ?, ?, ?)
+            ",
+            recipient_id,
+            snippet.name,
+            snippet.language,
+            snippet.code
+        )
+        .execute(self.pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .with_context(|| {
+            format!(
+                "failed to share snippet {} with {recipient_id}",
+                snippet.name
+            )
+        })
+    }
+}
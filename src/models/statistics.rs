@@ -0,0 +1,364 @@
+use crate::models::probability::Dist;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+
+/// The most values that will be summarised at once.
+const MAX_VALUES: usize = 100_000;
+/// The most bins a histogram is split into.
+const MAX_BINS: usize = 10;
+
+/// Descriptive statistics for a list of numbers.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    sorted: Vec<f64>,
+    pub mean: f64,
+    pub median: f64,
+    /// The most common values, unless every value is equally common.
+    pub modes: Vec<f64>,
+    /// The sample standard deviation, if there are at least two values.
+    pub std_dev: Option<f64>,
+    pub q1: f64,
+    pub q3: f64,
+}
+
+/// The result of a one-sample, two-sided t-test.
+#[derive(Debug, Clone, Copy)]
+pub struct TTest {
+    pub t: f64,
+    pub df: f64,
+    pub p: f64,
+}
+
+/// Returns the p-th quantile of sorted values, interpolating between them.
+fn quantile(sorted: &[f64], p: f64) -> f64 {
+    let position = p * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * position.fract()
+}
+
+impl Summary {
+    pub fn new(mut values: Vec<f64>) -> Result<Self> {
+        if values.is_empty() {
+            bail!("there are no numbers to summarise");
+        }
+        if values.len() > MAX_VALUES {
+            bail!("at most {MAX_VALUES} numbers can be summarised");
+        }
+        if values.iter().any(|value| !value.is_finite()) {
+            bail!("every value must be a finite number");
+        }
+        values.sort_by(f64::total_cmp);
+
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let std_dev = (values.len() > 1).then(|| {
+            let squares = values.iter().map(|value| (value - mean).powi(2));
+            (squares.sum::<f64>() / (n - 1.)).sqrt()
+        });
+
+        let mut counts = HashMap::new();
+        for value in &values {
+            *counts.entry(value.to_bits()).or_insert(0) += 1;
+        }
+        let highest = counts.values().copied().max().unwrap_or(0);
+        let modes = if counts.len() > 1 && counts.values().all(|count| *count == highest) {
+            Vec::new()
+        } else {
+            let mut modes = counts
+                .into_iter()
+                .filter(|(_, count)| *count == highest)
+                .map(|(bits, _)| f64::from_bits(bits))
+                .collect::<Vec<_>>();
+            modes.sort_by(f64::total_cmp);
+            modes
+        };
+
+        Ok(Self {
+            mean,
+            median: quantile(&values, 0.5),
+            modes,
+            std_dev,
+            q1: quantile(&values, 0.25),
+            q3: quantile(&values, 0.75),
+            sorted: values,
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        self.sorted.len()
+    }
+
+    pub fn min(&self) -> f64 {
+        self.sorted[0]
+    }
+
+    pub fn max(&self) -> f64 {
+        self.sorted[self.sorted.len() - 1]
+    }
+
+    /// Splits the range of values into equal bins, returning the lower bound and number of
+    /// values in each.
+    pub fn histogram(&self) -> Vec<(f64, usize)> {
+        // Sturges' rule
+        let bins = ((self.count() as f64).log2().ceil() as usize + 1).min(MAX_BINS);
+        let width = (self.max() - self.min()) / bins as f64;
+        if width == 0. {
+            return vec![(self.min(), self.count())];
+        }
+
+        let mut counts = vec![0; bins];
+        for value in &self.sorted {
+            let bin = ((value - self.min()) / width) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| (self.min() + i as f64 * width, count))
+            .collect()
+    }
+
+    /// Draws a box plot of the given width, like `|---[  :  ]-----|`.
+    pub fn box_plot(&self, width: usize) -> String {
+        let range = self.max() - self.min();
+        let column = |value: f64| {
+            if range == 0. {
+                width / 2
+            } else {
+                ((value - self.min()) / range * (width - 1) as f64).round() as usize
+            }
+        };
+
+        let mut plot = vec!['-'; width];
+        let (q1, q3) = (column(self.q1), column(self.q3));
+        plot[q1..=q3].fill(' ');
+        plot[q1] = '[';
+        plot[q3] = ']';
+        plot[column(self.median)] = ':';
+        plot[column(self.min())] = '|';
+        plot[column(self.max())] = '|';
+
+        plot.into_iter().collect()
+    }
+
+    /// Returns a confidence interval for the mean, based on Student's t-distribution.
+    pub fn mean_interval(&self, confidence: f64) -> Result<(f64, f64)> {
+        let std_dev = self
+            .std_dev
+            .with_context(|| "a confidence interval needs at least two values")?;
+        if confidence.is_nan() || confidence <= 0. || confidence >= 1. {
+            bail!("the confidence level must be between 0% and 100%");
+        }
+
+        let t = Dist::student_t((self.count() - 1) as f64)?;
+        let margin = t.inverse((1. + confidence) / 2.)? * std_dev / (self.count() as f64).sqrt();
+        Ok((self.mean - margin, self.mean + margin))
+    }
+
+    /// Tests whether the mean of the population these values came from could be `mu`.
+    pub fn t_test(&self, mu: f64) -> Result<TTest> {
+        let std_dev = self
+            .std_dev
+            .with_context(|| "a t-test needs at least two values")?;
+        if std_dev == 0. {
+            bail!("a t-test needs values which aren't all the same");
+        }
+
+        let df = (self.count() - 1) as f64;
+        let t = (self.mean - mu) / (std_dev / (self.count() as f64).sqrt());
+        let p = 2. * (1. - Dist::student_t(df)?.cdf(t.abs()));
+        Ok(TTest { t, df, p })
+    }
+}
+
+/// Splits a line of CSV into its fields, removing any quotes around them.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+        .into_iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
+
+/// Reads a column of numbers from CSV.  The column can be given by its name in the header
+/// row or by its 1-based position; otherwise the first numeric column is used.  The first
+/// row is taken to be a header if the column doesn't hold a number in it.
+pub fn parse_csv(csv: &str, column: Option<&str>) -> Result<Vec<f64>> {
+    let rows = csv
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(split_csv_line)
+        .collect::<Vec<_>>();
+    let Some(first) = rows.first() else {
+        bail!("the CSV is empty");
+    };
+    let is_number = |row: &[String], index: usize| {
+        row.get(index)
+            .is_some_and(|field| field.parse::<f64>().is_ok())
+    };
+
+    let index = match column {
+        Some(column) => match column.parse::<usize>() {
+            Ok(position) if position >= 1 => position - 1,
+            _ => first
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(column))
+                .with_context(|| format!("there's no column called {column}"))?,
+        },
+        None => {
+            let data = rows.get(1).unwrap_or(first);
+            (0..data.len())
+                .find(|index| is_number(data, *index))
+                .with_context(|| "the CSV has no numeric columns")?
+        }
+    };
+
+    let skip = usize::from(!is_number(first, index));
+    let mut values = Vec::new();
+    for (line, row) in rows.iter().enumerate().skip(skip) {
+        match row.get(index).map(String::as_str) {
+            None | Some("") => continue,
+            Some(field) => values.push(
+                field
+                    .parse::<f64>()
+                    .with_context(|| format!("{field} on row {} isn't a number", line + 1))?,
+            ),
+        }
+        if values.len() > MAX_VALUES {
+            bail!("at most {MAX_VALUES} numbers can be summarised");
+        }
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn splits_csv_lines() {
+        assert_eq!(split_csv_line("a, b ,c"), ["a", "b", "c"]);
+        assert_eq!(split_csv_line(r#""x, y",2"#), ["x, y", "2"]);
+        assert_eq!(split_csv_line(r#""say ""hi""",3"#), [r#"say "hi""#, "3"]);
+        assert_eq!(split_csv_line("1,,3,"), ["1", "", "3", ""]);
+    }
+
+    #[test]
+    fn parses_csv_columns() {
+        let csv = "name,height,\"weight, kg\"\nann,1.6,55\n\nbob,1.8,\ncat,1.7,70\n";
+        assert_eq!(parse_csv(csv, None).unwrap(), [1.6, 1.8, 1.7]);
+        assert_eq!(parse_csv(csv, Some("Weight, kg")).unwrap(), [55., 70.]);
+        assert_eq!(parse_csv(csv, Some("2")).unwrap(), [1.6, 1.8, 1.7]);
+        assert!(parse_csv(csv, Some("age")).is_err());
+        assert!(parse_csv(csv, Some("1")).is_err());
+
+        // Without a header, the first row is data.
+        assert_eq!(parse_csv("1,a\n2,b\n3,c", None).unwrap(), [1., 2., 3.]);
+        assert_eq!(parse_csv("4\n5", Some("1")).unwrap(), [4., 5.]);
+
+        assert!(parse_csv("", None).is_err());
+        assert!(parse_csv("a,b\nc,d", None).is_err());
+    }
+
+    #[test]
+    fn interpolates_quantiles() {
+        let sorted = [1., 2., 3., 4.];
+        assert_eq!(quantile(&sorted, 0.), 1.);
+        assert_eq!(quantile(&sorted, 0.25), 1.75);
+        assert_eq!(quantile(&sorted, 0.5), 2.5);
+        assert_eq!(quantile(&sorted, 1.), 4.);
+        assert_eq!(quantile(&[7.], 0.5), 7.);
+    }
+
+    #[test]
+    fn summarises_values() {
+        let summary = Summary::new(vec![5., 1., 4., 2., 3.]).unwrap();
+        assert_eq!(summary.count(), 5);
+        assert_eq!((summary.min(), summary.max()), (1., 5.));
+        assert_eq!(summary.mean, 3.);
+        assert_eq!((summary.q1, summary.median, summary.q3), (2., 3., 4.));
+        assert_close(summary.std_dev.unwrap(), 1.5811, 1e-4);
+
+        assert!(Summary::new(vec![]).is_err());
+        assert!(Summary::new(vec![1., f64::NAN]).is_err());
+        assert!(Summary::new(vec![1.]).unwrap().std_dev.is_none());
+    }
+
+    #[test]
+    fn finds_modes() {
+        let modes = |values: &[f64]| Summary::new(values.to_vec()).unwrap().modes;
+        assert_eq!(modes(&[1., 2., 2., 3.]), [2.]);
+        assert_eq!(modes(&[3., 1., 3., 1., 2.]), [1., 3.]);
+        assert_eq!(modes(&[4., 4., 4.]), [4.]);
+
+        // When every value is equally common, none of them is a mode.
+        assert!(modes(&[1., 2., 3.]).is_empty());
+        assert!(modes(&[1., 1., 2., 2.]).is_empty());
+    }
+
+    #[test]
+    fn bins_histograms() {
+        let summary = Summary::new((1..=8).map(f64::from).collect()).unwrap();
+        assert_eq!(
+            summary.histogram(),
+            [(1., 2), (2.75, 2), (4.5, 2), (6.25, 2)]
+        );
+
+        let summary = Summary::new(vec![5.; 3]).unwrap();
+        assert_eq!(summary.histogram(), [(5., 3)]);
+
+        let summary = Summary::new((0..10_000).map(f64::from).collect()).unwrap();
+        let histogram = summary.histogram();
+        assert_eq!(histogram.len(), MAX_BINS);
+        assert_eq!(
+            histogram.iter().map(|(_, count)| count).sum::<usize>(),
+            10_000
+        );
+    }
+
+    #[test]
+    fn intervals_and_tests_match_tables() {
+        let summary = Summary::new(vec![1., 2., 3., 4., 5.]).unwrap();
+
+        let (lower, upper) = summary.mean_interval(0.95).unwrap();
+        assert_close(lower, 1.0368, 1e-4);
+        assert_close(upper, 4.9632, 1e-4);
+        assert!(summary.mean_interval(1.).is_err());
+        assert!(summary.mean_interval(f64::NAN).is_err());
+
+        let test = summary.t_test(1.5).unwrap();
+        assert_close(test.t, 2.1213, 1e-4);
+        assert_eq!(test.df, 4.);
+        assert_close(test.p, 0.1012, 1e-4);
+        assert_close(summary.t_test(3.).unwrap().p, 1., 1e-9);
+
+        assert!(Summary::new(vec![1.]).unwrap().t_test(0.).is_err());
+        assert!(Summary::new(vec![2., 2.]).unwrap().t_test(0.).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use serde::Deserialize;
+
+/// Pollutant concentrations, in μg/m³.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Pollutants {
+    pub co: f32,
+    pub no2: f32,
+    pub o3: f32,
+    pub so2: f32,
+    pub pm2_5: f32,
+    pub pm10: f32,
+}
+
+/// The air quality at a location, as reported by OpenWeatherMap.
+#[derive(Clone, Debug)]
+pub struct AirQuality {
+    pub level: AqiLevel,
+    pub pollutants: Pollutants,
+}
+
+/// OpenWeatherMap's five point air quality index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AqiLevel {
+    Good,
+    Fair,
+    Moderate,
+    Poor,
+    VeryPoor,
+}
+
+impl AqiLevel {
+    /// Returns the level for an index between 1 and 5.
+    pub fn from_index(index: u8) -> Option<Self> {
+        match index {
+            1 => Some(AqiLevel::Good),
+            2 => Some(AqiLevel::Fair),
+            3 => Some(AqiLevel::Moderate),
+            4 => Some(AqiLevel::Poor),
+            5 => Some(AqiLevel::VeryPoor),
+            _ => None,
+        }
+    }
+
+    pub fn index(self) -> u8 {
+        self as u8 + 1
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AqiLevel::Good => "Good",
+            AqiLevel::Fair => "Fair",
+            AqiLevel::Moderate => "Moderate",
+            AqiLevel::Poor => "Poor",
+            AqiLevel::VeryPoor => "Very poor",
+        }
+    }
+
+    /// Returns the colour conventionally used for this level, as an RGB value.
+    pub fn colour(self) -> u32 {
+        match self {
+            AqiLevel::Good => 0x2ecc71,
+            AqiLevel::Fair => 0xf1c40f,
+            AqiLevel::Moderate => 0xe67e22,
+            AqiLevel::Poor => 0xe74c3c,
+            AqiLevel::VeryPoor => 0x8e44ad,
+        }
+    }
+
+    /// Returns advice for people who are sensitive to air pollution.
+    pub fn advice(self) -> &'static str {
+        match self {
+            AqiLevel::Good | AqiLevel::Fair => "Air quality is fine for outdoor activities.",
+            AqiLevel::Moderate => {
+                "Sensitive groups should consider reducing prolonged outdoor exertion."
+            }
+            AqiLevel::Poor => "Sensitive groups should avoid prolonged outdoor exertion.",
+            AqiLevel::VeryPoor => "Everyone should avoid outdoor exertion.",
+        }
+    }
+}
+
+/// Returns the WHO exposure category for the given UV index.
+pub fn uv_category(uvi: f32) -> &'static str {
+    match uvi.round() as u32 {
+        0..=2 => "Low",
+        3..=5 => "Moderate",
+        6 | 7 => "High",
+        8..=10 => "Very high",
+        _ => "Extreme",
+    }
+}
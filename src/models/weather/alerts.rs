@@ -0,0 +1,316 @@
+use crate::models::weather::{OpenWeatherMapClient, WeatherError};
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serenity::async_trait;
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
+
+/// A severe weather alert issued by a national weather agency.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeatherAlert {
+    pub sender_name: String,
+    pub event: String,
+    pub start: i64,
+    pub end: i64,
+    pub description: String,
+}
+
+impl WeatherAlert {
+    /// Alerts don't come with an identifier, so derive one from the fields which identify
+    /// an alert to a human.
+    pub fn id(&self) -> String {
+        format!("{}|{}|{}", self.sender_name, self.event, self.start)
+    }
+}
+
+/// Somewhere alerts for a location can be fetched from.
+#[async_trait]
+pub trait AlertSource {
+    async fn alerts(&self, lat: &str, lon: &str) -> Result<Vec<WeatherAlert>, WeatherError>;
+}
+
+#[async_trait]
+impl AlertSource for OpenWeatherMapClient {
+    async fn alerts(&self, lat: &str, lon: &str) -> Result<Vec<WeatherAlert>, WeatherError> {
+        self.get_alerts(lat, lon).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertSubscription {
+    pub id: i64,
+    pub guild: i64,
+    pub channel: i64,
+    pub location_name: String,
+    pub lat: String,
+    pub lon: String,
+}
+
+pub struct AlertStore<'pool> {
+    pool: &'pool Pool<Sqlite>,
+}
+
+impl<'pool> AlertStore<'pool> {
+    pub fn new(pool: &'pool Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    /// Subscribes the given channel to alerts for a location.  Returns false if the channel
+    /// was already subscribed to that location.
+    pub async fn subscribe(
+        &self,
+        guild: i64,
+        channel: i64,
+        location_name: &str,
+        lat: &str,
+        lon: &str,
+    ) -> Result<bool> {
+        sqlx::query!(
+            "
+        INSERT OR IGNORE INTO weather_alert_subscriptions (guild, channel, location_name, lat, lon)
+        VALUES (?, ?, ?, ?, ?)
+            ",
+            guild,
+            channel,
+            location_name,
+            lat,
+            lon
+        )
+        .execute(self.pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .with_context(|| format!("failed to subscribe channel {channel} to alerts"))
+    }
+
+    /// Removes all of the given channel's subscriptions, returning how many there were.
+    pub async fn unsubscribe(&self, channel: i64) -> Result<u64> {
+        sqlx::query!(
+            "DELETE FROM weather_alert_subscriptions WHERE channel = ?",
+            channel
+        )
+        .execute(self.pool)
+        .await
+        .map(|result| result.rows_affected())
+        .with_context(|| format!("failed to unsubscribe channel {channel} from alerts"))
+    }
+
+    pub async fn subscriptions(&self) -> Result<Vec<AlertSubscription>> {
+        sqlx::query_as!(
+            AlertSubscription,
+            "SELECT id, guild, channel, location_name, lat, lon FROM weather_alert_subscriptions"
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| "failed to get alert subscriptions")
+    }
+
+    /// Returns whether an alert has already been posted for a subscription.
+    async fn is_posted(&self, subscription_id: i64, alert_id: &str) -> Result<bool> {
+        sqlx::query_scalar!(
+            r#"
+        SELECT EXISTS (
+            SELECT 1 FROM posted_weather_alerts WHERE subscription_id = ? AND alert_id = ?
+        ) as "posted!: bool"
+            "#,
+            subscription_id,
+            alert_id
+        )
+        .fetch_one(self.pool)
+        .await
+        .with_context(|| format!("failed to check whether alert {alert_id} was posted"))
+    }
+
+    /// Records that an alert has been posted for a subscription.  Returns false if it had
+    /// already been posted.
+    pub async fn mark_posted(&self, subscription_id: i64, alert_id: &str) -> Result<bool> {
+        let posted_at = Utc::now().timestamp();
+        sqlx::query!(
+            "
+        INSERT OR IGNORE INTO posted_weather_alerts (subscription_id, alert_id, posted_at)
+        VALUES (?, ?, ?)
+            ",
+            subscription_id,
+            alert_id,
+            posted_at
+        )
+        .execute(self.pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .with_context(|| format!("failed to mark alert {alert_id} as posted"))
+    }
+
+    /// Forgets alerts posted long enough ago that they can no longer be active.
+    async fn clear(&self) -> Result<()> {
+        let threshold = (Utc::now() - Duration::days(30)).timestamp();
+        sqlx::query!(
+            "DELETE FROM posted_weather_alerts WHERE posted_at <= ?",
+            threshold
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| "failed to clear old posted alerts")
+    }
+}
+
+/// Checks every subscription for alerts which haven't been posted yet.
+pub struct AlertScheduler<S> {
+    store: AlertStore<'static>,
+    source: S,
+}
+
+impl<S: AlertSource> AlertScheduler<S> {
+    pub fn new(store: AlertStore<'static>, source: S) -> Self {
+        Self { store, source }
+    }
+
+    /// Polls the alert source once, returning each new alert along with the subscription it
+    /// should be posted to.  Alerts are returned again by later polls until they are passed
+    /// to [`AlertScheduler::mark_posted`].
+    pub async fn poll(&self) -> Result<Vec<(AlertSubscription, WeatherAlert)>> {
+        self.store.clear().await?;
+
+        // Only look up each location once, however many channels are subscribed to it.
+        let mut alerts_by_location: HashMap<(String, String), Vec<WeatherAlert>> = HashMap::new();
+        let mut new_alerts = Vec::new();
+        let mut seen = HashSet::new();
+
+        for subscription in self.store.subscriptions().await? {
+            let key = (subscription.lat.clone(), subscription.lon.clone());
+            if !alerts_by_location.contains_key(&key) {
+                match self.source.alerts(&key.0, &key.1).await {
+                    Ok(alerts) => {
+                        alerts_by_location.insert(key.clone(), alerts);
+                    }
+                    Err(why) => {
+                        warn!(
+                            "failed to get alerts for {}: {why:?}",
+                            subscription.location_name
+                        );
+                        continue;
+                    }
+                }
+            }
+
+            for alert in &alerts_by_location[&key] {
+                let alert_id = alert.id();
+                if seen.insert((subscription.id, alert_id.clone()))
+                    && !self.store.is_posted(subscription.id, &alert_id).await?
+                {
+                    new_alerts.push((subscription.clone(), alert.clone()));
+                }
+            }
+        }
+
+        Ok(new_alerts)
+    }
+
+    /// Records that an alert has been posted to a subscription's channel.
+    pub async fn mark_posted(
+        &self,
+        subscription: &AlertSubscription,
+        alert: &WeatherAlert,
+    ) -> Result<()> {
+        self.store
+            .mark_posted(subscription.id, &alert.id())
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Serves canned alerts, and remembers which locations were asked about.
+    #[derive(Default)]
+    struct FakeSource {
+        alerts: HashMap<(String, String), Vec<WeatherAlert>>,
+        requests: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait]
+    impl AlertSource for FakeSource {
+        async fn alerts(&self, lat: &str, lon: &str) -> Result<Vec<WeatherAlert>, WeatherError> {
+            let key = (lat.to_string(), lon.to_string());
+            self.requests.lock().unwrap().push(key.clone());
+            self.alerts.get(&key).cloned().ok_or(WeatherError::NotFound)
+        }
+    }
+
+    fn alert(event: &str) -> WeatherAlert {
+        WeatherAlert {
+            sender_name: String::from("Met Office"),
+            event: event.to_string(),
+            start: 1697460000,
+            end: 1697500000,
+            description: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn polls_each_location_once() {
+        let pool = Box::leak(Box::new(crate::models::test_pool().await));
+        let store = AlertStore::new(pool);
+        for (channel, name, lat) in [
+            (1, "London", "51.5"),
+            (2, "London", "51.5"),
+            (3, "Atlantis", "0"),
+        ] {
+            assert!(store.subscribe(1, channel, name, lat, "0").await.unwrap());
+        }
+        assert!(!store.subscribe(1, 1, "London", "51.5", "0").await.unwrap());
+
+        let mut source = FakeSource::default();
+        source.alerts.insert(
+            (String::from("51.5"), String::from("0")),
+            vec![alert("Wind"), alert("Wind"), alert("Rain")],
+        );
+        let scheduler = AlertScheduler::new(AlertStore::new(pool), source);
+
+        // Atlantis can't be found, so it is skipped, and the repeated alert is only posted once.
+        let new_alerts = scheduler.poll().await.unwrap();
+        let mut posts = new_alerts
+            .iter()
+            .map(|(subscription, alert)| (subscription.channel, alert.event.as_str()))
+            .collect::<Vec<_>>();
+        posts.sort();
+        assert_eq!(posts, [(1, "Rain"), (1, "Wind"), (2, "Rain"), (2, "Wind")]);
+
+        let mut requests = scheduler.source.requests.lock().unwrap().clone();
+        requests.sort();
+        assert_eq!(
+            requests,
+            [
+                (String::from("0"), String::from("0")),
+                (String::from("51.5"), String::from("0"))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn repeats_alerts_until_posted() {
+        let pool = Box::leak(Box::new(crate::models::test_pool().await));
+        let store = AlertStore::new(pool);
+        store.subscribe(1, 1, "London", "51.5", "0").await.unwrap();
+
+        let mut source = FakeSource::default();
+        source.alerts.insert(
+            (String::from("51.5"), String::from("0")),
+            vec![alert("Wind"), alert("Rain")],
+        );
+        let scheduler = AlertScheduler::new(store, source);
+
+        let new_alerts = scheduler.poll().await.unwrap();
+        assert_eq!(new_alerts.len(), 2);
+        let (subscription, posted) = &new_alerts[0];
+        scheduler.mark_posted(subscription, posted).await.unwrap();
+
+        let new_alerts = scheduler.poll().await.unwrap();
+        assert_eq!(new_alerts.len(), 1);
+        assert_ne!(new_alerts[0].1.event, posted.event);
+    }
+}
@@ -0,0 +1,113 @@
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+use std::fmt;
+
+/// Everything that can go wrong while looking up the weather.  The `Display` implementation
+/// gives a message suitable for showing to users.
+#[derive(Debug)]
+pub enum WeatherError {
+    /// The requested place (or its weather) could not be found.
+    NotFound,
+    /// We are sending too many requests to an upstream service.
+    RateLimited,
+    /// The configured API key was rejected.
+    BadApiKey,
+    /// Any other failure talking to, or understanding, an upstream service.
+    Upstream(String),
+}
+
+impl fmt::Display for WeatherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeatherError::NotFound => write!(f, "I couldn't find that place."),
+            WeatherError::RateLimited => write!(
+                f,
+                "The weather service is busy right now, try again in a minute."
+            ),
+            WeatherError::BadApiKey => write!(
+                f,
+                "The weather service rejected our API key, please let the bot owner know."
+            ),
+            WeatherError::Upstream(message) => {
+                write!(f, "The weather service returned an error: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WeatherError {}
+
+impl From<reqwest::Error> for WeatherError {
+    fn from(e: reqwest::Error) -> Self {
+        WeatherError::Upstream(e.to_string())
+    }
+}
+
+/// The body sent alongside an error status.  OpenWeatherMap calls the explanation
+/// `message`, whereas Open-Meteo calls it `reason`.
+#[derive(Clone, Debug, Deserialize)]
+pub(super) struct ErrorResponse {
+    #[serde(alias = "reason")]
+    pub message: String,
+}
+
+/// Maps an unsuccessful response to the appropriate error.
+pub(super) async fn check_response(response: Response) -> Result<Response, WeatherError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(error_for_status(status, &body))
+}
+
+/// Returns the error for an unsuccessful status and the body sent alongside it.
+fn error_for_status(status: StatusCode, body: &str) -> WeatherError {
+    match status {
+        StatusCode::UNAUTHORIZED => WeatherError::BadApiKey,
+        StatusCode::NOT_FOUND => WeatherError::NotFound,
+        StatusCode::TOO_MANY_REQUESTS => WeatherError::RateLimited,
+        status => {
+            let message = serde_json::from_str::<ErrorResponse>(body)
+                .map(|body| body.message)
+                .unwrap_or_else(|_| status.to_string());
+            WeatherError::Upstream(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_error_bodies() {
+        let error = |status, body| error_for_status(StatusCode::from_u16(status).unwrap(), body);
+
+        assert!(matches!(
+            error(401, include_str!("fixtures/owm_401.json")),
+            WeatherError::BadApiKey
+        ));
+        assert!(matches!(
+            error(404, include_str!("fixtures/owm_404.json")),
+            WeatherError::NotFound
+        ));
+        assert!(matches!(
+            error(429, include_str!("fixtures/owm_429.json")),
+            WeatherError::RateLimited
+        ));
+        assert!(matches!(
+            error(400, include_str!("fixtures/owm_400.json")),
+            WeatherError::Upstream(message) if message == "wrong latitude"
+        ));
+        assert!(matches!(
+            error(400, include_str!("fixtures/open_meteo_400.json")),
+            WeatherError::Upstream(message) if message.starts_with("Latitude must be in range")
+        ));
+        assert!(matches!(
+            error(502, "<html>Bad Gateway</html>"),
+            WeatherError::Upstream(message) if message == "502 Bad Gateway"
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use sqlx::{Pool, Sqlite};
+
+/// Geocoded locations are kept for this long before being looked up again.
+const TTL_DAYS: i64 = 90;
+
+struct GeocodeEntry {
+    display_name: String,
+    lat: String,
+    lon: String,
+}
+
+/// Normalises a search query so that trivially different queries share a cache entry.
+fn normalise(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[derive(Clone)]
+pub(super) struct GeocodeCache<'pool> {
+    pool: &'pool Pool<Sqlite>,
+}
+
+impl<'pool> GeocodeCache<'pool> {
+    pub fn new(pool: &'pool Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the cached `(display_name, lat, lon)` for the given query, if it is fresh.
+    pub async fn get(&self, query: &str) -> Result<Option<(String, String, String)>> {
+        let query = normalise(query);
+        let threshold = (Utc::now() - Duration::days(TTL_DAYS)).timestamp();
+
+        sqlx::query_as!(
+            GeocodeEntry,
+            "
+        SELECT display_name, lat, lon
+        FROM geocode_cache
+        WHERE query = ?
+        AND last_updated > ?
+            ",
+            query,
+            threshold
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map(|entry| entry.map(|e| (e.display_name, e.lat, e.lon)))
+        .with_context(|| format!("failed to get cached location for {query}"))
+    }
+
+    pub async fn insert(
+        &self,
+        query: &str,
+        display_name: &str,
+        lat: &str,
+        lon: &str,
+    ) -> Result<()> {
+        let query = normalise(query);
+        let last_updated = Utc::now().timestamp();
+
+        sqlx::query!(
+            "
+        INSERT OR REPLACE INTO geocode_cache (query, display_name, lat, lon, last_updated)
+        VALUES (?, ?, ?, ?, ?)
+            ",
+            query,
+            display_name,
+            lat,
+            lon,
+            last_updated
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to cache location for {query}"))
+    }
+
+    /// Removes the entry for the given query, or every entry if no query is given.
+    /// Returns the number of entries removed.
+    pub async fn purge(&self, query: Option<&str>) -> Result<u64> {
+        let result = match query.map(normalise) {
+            Some(query) => {
+                sqlx::query!("DELETE FROM geocode_cache WHERE query = ?", query)
+                    .execute(self.pool)
+                    .await
+            }
+            None => {
+                sqlx::query!("DELETE FROM geocode_cache")
+                    .execute(self.pool)
+                    .await
+            }
+        };
+
+        result
+            .map(|result| result.rows_affected())
+            .with_context(|| "failed to purge geocode cache")
+    }
+}
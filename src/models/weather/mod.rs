@@ -0,0 +1,304 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::async_trait;
+
+use crate::client::make_client;
+use crate::models::weather::error::check_response;
+use crate::models::weather::geocache::GeocodeCache;
+use sqlx::{Pool, Sqlite};
+use std::time::Duration;
+use tracing::warn;
+
+pub mod air_quality;
+pub mod alerts;
+mod error;
+mod geocache;
+mod open_meteo;
+mod openweathermap;
+
+pub use error::WeatherError;
+pub use open_meteo::OpenMeteoClient;
+pub use openweathermap::OpenWeatherMapClient;
+
+type RateLimiter = governor::RateLimiter<
+    governor::state::NotKeyed,
+    governor::state::InMemoryState,
+    governor::clock::DefaultClock,
+    governor::middleware::NoOpMiddleware,
+>;
+
+fn rate_limiter() -> &'static RateLimiter {
+    static PS_RATE_LIMITER: once_cell::sync::OnceCell<RateLimiter> =
+        once_cell::sync::OnceCell::new();
+    PS_RATE_LIMITER.get_or_init(|| {
+        governor::RateLimiter::direct(
+            governor::Quota::with_period(Duration::from_millis(1500)).unwrap(),
+        )
+    })
+}
+
+#[derive(Clone)]
+pub struct NominatimClient {
+    limiter: &'static RateLimiter,
+    client: reqwest::Client,
+    cache: GeocodeCache<'static>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct NominatimRequest {
+    q: String,
+    format: &'static str,
+    limit: u8,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct NominatimElement {
+    lat: String,
+    lon: String,
+    display_name: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct NominatimResponse(Vec<NominatimElement>);
+
+impl NominatimClient {
+    pub fn new(pool: &'static Pool<Sqlite>) -> Self {
+        Self {
+            limiter: rate_limiter(),
+            client: make_client(),
+            cache: GeocodeCache::new(pool),
+        }
+    }
+
+    /// Return the coordinates associated with the given query string.
+    pub async fn search<T: Into<String>>(
+        &self,
+        query: T,
+    ) -> Result<Option<(String, String, String)>, WeatherError> {
+        let query = query.into();
+
+        // Cached locations don't count against Nominatim's rate limit.  The cache is only an
+        // optimisation, so failing to use it shouldn't fail the search.
+        match self.cache.get(&query).await {
+            Ok(location @ Some(_)) => return Ok(location),
+            Ok(None) => {}
+            Err(why) => warn!("failed to read geocode cache: {why:?}"),
+        }
+
+        let location = self._search(&query).await?;
+        if let Some((display_name, lat, lon)) = &location {
+            if let Err(why) = self.cache.insert(&query, display_name, lat, lon).await {
+                warn!("failed to write geocode cache: {why:?}");
+            }
+        }
+
+        Ok(location)
+    }
+
+    /// Removes the cached location for the given query, or all cached locations.
+    pub async fn purge_cache(&self, query: Option<&str>) -> Result<u64> {
+        self.cache.purge(query).await
+    }
+
+    async fn _search(&self, query: &str) -> Result<Option<(String, String, String)>, WeatherError> {
+        let request = NominatimRequest {
+            q: query.to_string(),
+            format: "jsonv2",
+            limit: 1,
+        };
+
+        self.limiter.until_ready().await;
+        let response = self
+            .client
+            .get("https://nominatim.openstreetmap.org/search.php")
+            .query(&request)
+            .send()
+            .await?;
+        let response = check_response(response).await?;
+
+        // Note: by limit=1 above we know that the last element of payload, if it exists,
+        // will also be the first.
+        let mut payload: NominatimResponse = response.json().await?;
+        Ok(payload.0.pop().map(|e| (e.display_name, e.lat, e.lon)))
+    }
+}
+
+/// Returns the 16-point compass direction for the given bearing in degrees.
+pub fn compass_point(degrees: f32) -> &'static str {
+    const POINTS: [&str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+        "NW", "NNW",
+    ];
+
+    let index = (degrees.rem_euclid(360.) / 22.5).round() as usize % POINTS.len();
+    POINTS[index]
+}
+
+/// Converts a UNIX timestamp to a local time at the given offset (in seconds) from UTC.
+pub fn local_time(timestamp: i64, utc_offset: i32) -> Option<DateTime<FixedOffset>> {
+    let offset = FixedOffset::east_opt(utc_offset)?;
+    Some(
+        Utc.timestamp_opt(timestamp, 0)
+            .single()?
+            .with_timezone(&offset),
+    )
+}
+
+/// The current conditions at a location, in metric units.
+#[derive(Clone, Debug)]
+pub struct CurrentWeather {
+    pub description: String,
+    pub emoji: &'static str,
+    pub icon_url: Option<String>,
+    pub temp: f32,
+    pub feels_like: f32,
+    /// Relative humidity as a percentage.
+    pub humidity: f32,
+    /// Atmospheric pressure (at sea level) in hPa.
+    pub pressure: f32,
+    /// Wind speed in metres per second.
+    pub wind_speed: f32,
+    /// Wind direction in meteorological degrees.
+    pub wind_deg: f32,
+    /// Cloud cover as a percentage.
+    pub cloud_cover: f32,
+    /// Visibility in metres.
+    pub visibility: Option<f32>,
+    /// The UV index, if the provider reports it.
+    pub uv_index: Option<f32>,
+    pub sunrise: Option<i64>,
+    pub sunset: Option<i64>,
+    /// Offset of the location from UTC in seconds.
+    pub utc_offset: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct ForecastEntry {
+    pub dt: i64,
+    pub temp: f32,
+    pub temp_min: f32,
+    pub temp_max: f32,
+    pub emoji: &'static str,
+    pub is_day: bool,
+    /// Probability of precipitation, between 0 and 1.
+    pub pop: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Forecast {
+    /// Offset of the location from UTC in seconds.
+    pub utc_offset: i32,
+    pub entries: Vec<ForecastEntry>,
+}
+
+/// A summary of the forecast for a single (local) day.
+#[derive(Clone, Debug)]
+pub struct DailyForecast {
+    pub date: NaiveDate,
+    pub temp_min: f32,
+    pub temp_max: f32,
+    pub emoji: &'static str,
+    pub pop: f32,
+}
+
+impl Forecast {
+    fn local_time(&self, dt: i64) -> Option<DateTime<FixedOffset>> {
+        local_time(dt, self.utc_offset)
+    }
+
+    /// Summarises the forecast entries per local day.
+    pub fn daily(&self) -> Vec<DailyForecast> {
+        let mut days: Vec<(DailyForecast, Vec<&'static str>)> = Vec::new();
+
+        for entry in &self.entries {
+            let Some(local) = self.local_time(entry.dt) else {
+                continue;
+            };
+            // Describe each day by its daytime conditions.
+            let emoji = Some(entry.emoji).filter(|_| entry.is_day);
+
+            match days.last_mut() {
+                Some((day, emojis)) if day.date == local.date_naive() => {
+                    day.temp_min = day.temp_min.min(entry.temp_min);
+                    day.temp_max = day.temp_max.max(entry.temp_max);
+                    day.pop = day.pop.max(entry.pop);
+                    emojis.extend(emoji);
+                }
+                _ => days.push((
+                    DailyForecast {
+                        date: local.date_naive(),
+                        temp_min: entry.temp_min,
+                        temp_max: entry.temp_max,
+                        emoji: entry.emoji,
+                        pop: entry.pop,
+                    },
+                    emoji.into_iter().collect(),
+                )),
+            }
+        }
+
+        days.into_iter()
+            .map(|(mut day, emojis)| {
+                let most_common = emojis
+                    .iter()
+                    .max_by_key(|emoji| emojis.iter().filter(|other| other == emoji).count());
+                if let Some(emoji) = most_common {
+                    day.emoji = emoji;
+                }
+                day
+            })
+            .collect()
+    }
+
+    /// Returns the forecast entries covering the next `hours` hours, with their local times.
+    pub fn hourly(&self, hours: i64) -> Vec<(DateTime<FixedOffset>, &ForecastEntry)> {
+        // Skip over any entries which have already passed.
+        let now = Utc::now().timestamp();
+        let upcoming: Vec<_> = self
+            .entries
+            .iter()
+            .skip_while(|entry| entry.dt + 60 * 60 <= now)
+            .collect();
+
+        let Some(first) = upcoming.first().map(|entry| entry.dt) else {
+            return Vec::new();
+        };
+
+        upcoming
+            .into_iter()
+            .take_while(|entry| entry.dt < first + hours * 60 * 60)
+            .filter_map(|entry| Some((self.local_time(entry.dt)?, entry)))
+            .collect()
+    }
+}
+
+/// A source of current conditions and forecasts.
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    async fn current(&self, lat: &str, lon: &str) -> Result<CurrentWeather, WeatherError>;
+
+    async fn forecast(&self, lat: &str, lon: &str) -> Result<Forecast, WeatherError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nominatim_results() {
+        let mut response: NominatimResponse =
+            serde_json::from_str(include_str!("fixtures/nominatim_search.json")).unwrap();
+        let place = response.0.pop().unwrap();
+        assert_eq!(
+            place.display_name,
+            "London, Greater London, England, United Kingdom"
+        );
+        assert_eq!(place.lat, "51.5073219");
+        assert_eq!(place.lon, "-0.1276474");
+
+        let response: NominatimResponse =
+            serde_json::from_str(include_str!("fixtures/nominatim_empty.json")).unwrap();
+        assert!(response.0.is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::client::make_client;
+use crate::models::weather::error::check_response;
+use crate::models::weather::{
+    CurrentWeather, Forecast, ForecastEntry, WeatherError, WeatherProvider,
+};
+use serde::{Deserialize, Serialize};
+use serenity::async_trait;
+
+const CURRENT_VARIABLES: &str = "temperature_2m,apparent_temperature,relative_humidity_2m,\
+pressure_msl,wind_speed_10m,wind_direction_10m,cloud_cover,visibility,uv_index,weather_code,is_day";
+const HOURLY_VARIABLES: &str = "temperature_2m,weather_code,precipitation_probability,is_day";
+
+/// Client for the keyless Open-Meteo forecast API.
+pub struct OpenMeteoClient {
+    client: reqwest::Client,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OpenMeteoQuery<'a> {
+    latitude: &'a str,
+    longitude: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hourly: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    daily: Option<&'static str>,
+    forecast_days: u8,
+    timezone: &'static str,
+    timeformat: &'static str,
+    wind_speed_unit: &'static str,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OMCurrent {
+    temperature_2m: f32,
+    apparent_temperature: f32,
+    relative_humidity_2m: f32,
+    pressure_msl: f32,
+    wind_speed_10m: f32,
+    wind_direction_10m: f32,
+    cloud_cover: f32,
+    visibility: Option<f32>,
+    uv_index: Option<f32>,
+    weather_code: u8,
+    is_day: u8,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OMHourly {
+    time: Vec<i64>,
+    temperature_2m: Vec<f32>,
+    weather_code: Vec<u8>,
+    precipitation_probability: Vec<Option<f32>>,
+    is_day: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OMDaily {
+    sunrise: Vec<i64>,
+    sunset: Vec<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OpenMeteoResponse {
+    utc_offset_seconds: i32,
+    current: Option<OMCurrent>,
+    hourly: Option<OMHourly>,
+    daily: Option<OMDaily>,
+}
+
+/// Returns an emoji for the given WMO weather interpretation code.
+fn emoji_for_code(code: u8, is_day: bool) -> &'static str {
+    match code {
+        0 if is_day => ":sunny:",
+        0 => ":crescent_moon:",
+        1 | 2 if is_day => ":white_sun_cloud:",
+        1..=3 => ":cloud:",
+        45 | 48 => ":fog:",
+        51..=67 | 80..=82 => ":cloud_rain:",
+        71..=77 | 85 | 86 => ":cloud_snow:",
+        95..=99 => ":cloud_lightning:",
+        _ => ":thermometer:",
+    }
+}
+
+/// Returns a description of the given WMO weather interpretation code.
+fn description_for_code(code: u8) -> &'static str {
+    match code {
+        0 => "clear sky",
+        1 => "mainly clear",
+        2 => "partly cloudy",
+        3 => "overcast",
+        45 | 48 => "fog",
+        51..=55 => "drizzle",
+        56 | 57 => "freezing drizzle",
+        61..=65 => "rain",
+        66 | 67 => "freezing rain",
+        71..=75 => "snow",
+        77 => "snow grains",
+        80..=82 => "rain showers",
+        85 | 86 => "snow showers",
+        95 => "thunderstorm",
+        96 | 99 => "thunderstorm with hail",
+        _ => "unknown conditions",
+    }
+}
+
+impl OpenMeteoClient {
+    pub fn new() -> Self {
+        Self {
+            client: make_client(),
+        }
+    }
+
+    async fn _get(&self, query: &OpenMeteoQuery<'_>) -> Result<OpenMeteoResponse, WeatherError> {
+        let response = self
+            .client
+            .get("https://api.open-meteo.com/v1/forecast")
+            .query(query)
+            .send()
+            .await?;
+
+        Ok(check_response(response).await?.json().await?)
+    }
+
+    fn query<'a>(lat: &'a str, lon: &'a str, forecast_days: u8) -> OpenMeteoQuery<'a> {
+        OpenMeteoQuery {
+            latitude: lat,
+            longitude: lon,
+            current: None,
+            hourly: None,
+            daily: None,
+            forecast_days,
+            timezone: "auto",
+            timeformat: "unixtime",
+            wind_speed_unit: "ms",
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenMeteoClient {
+    async fn current(&self, lat: &str, lon: &str) -> Result<CurrentWeather, WeatherError> {
+        let query = OpenMeteoQuery {
+            current: Some(CURRENT_VARIABLES),
+            daily: Some("sunrise,sunset"),
+            ..Self::query(lat, lon, 1)
+        };
+        let response = self._get(&query).await?;
+
+        let current = response
+            .current
+            .ok_or_else(|| WeatherError::Upstream(String::from("missing current conditions")))?;
+        let daily = response.daily;
+
+        Ok(CurrentWeather {
+            description: description_for_code(current.weather_code).to_string(),
+            emoji: emoji_for_code(current.weather_code, current.is_day != 0),
+            icon_url: None,
+            temp: current.temperature_2m,
+            feels_like: current.apparent_temperature,
+            humidity: current.relative_humidity_2m,
+            pressure: current.pressure_msl,
+            wind_speed: current.wind_speed_10m,
+            wind_deg: current.wind_direction_10m,
+            cloud_cover: current.cloud_cover,
+            visibility: current.visibility,
+            uv_index: current.uv_index,
+            sunrise: daily
+                .as_ref()
+                .and_then(|daily| daily.sunrise.first().copied()),
+            sunset: daily
+                .as_ref()
+                .and_then(|daily| daily.sunset.first().copied()),
+            utc_offset: response.utc_offset_seconds,
+        })
+    }
+
+    async fn forecast(&self, lat: &str, lon: &str) -> Result<Forecast, WeatherError> {
+        let query = OpenMeteoQuery {
+            hourly: Some(HOURLY_VARIABLES),
+            ..Self::query(lat, lon, 5)
+        };
+        let response = self._get(&query).await?;
+
+        let hourly = response
+            .hourly
+            .ok_or_else(|| WeatherError::Upstream(String::from("missing hourly forecast")))?;
+
+        let entries = hourly
+            .time
+            .iter()
+            .zip(&hourly.temperature_2m)
+            .zip(&hourly.weather_code)
+            .zip(&hourly.precipitation_probability)
+            .zip(&hourly.is_day)
+            .map(|((((&dt, &temp), &code), pop), &is_day)| ForecastEntry {
+                dt,
+                temp,
+                temp_min: temp,
+                temp_max: temp,
+                emoji: emoji_for_code(code, is_day != 0),
+                is_day: is_day != 0,
+                pop: pop.unwrap_or_default() / 100.,
+            })
+            .collect();
+
+        Ok(Forecast {
+            utc_offset: response.utc_offset_seconds,
+            entries,
+        })
+    }
+}
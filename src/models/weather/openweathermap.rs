@@ -0,0 +1,440 @@
+use crate::client::make_client;
+use crate::models::weather::air_quality::{AirQuality, AqiLevel, Pollutants};
+use crate::models::weather::alerts::WeatherAlert;
+use crate::models::weather::error::check_response;
+use crate::models::weather::{
+    CurrentWeather, Forecast, ForecastEntry, WeatherError, WeatherProvider,
+};
+use serde::{Deserialize, Serialize};
+use serenity::async_trait;
+use tracing::warn;
+
+pub struct OpenWeatherMapClient {
+    client: reqwest::Client,
+    api_key: String,
+    /// Whether the API key is subscribed to One Call 3.0.
+    one_call: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OpenWeatherMapQuery<'a> {
+    lat: &'a str,
+    lon: &'a str,
+    #[serde(rename = "appid")]
+    app_id: &'a str,
+    units: &'static str,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OpenWeatherMapOneCallQuery<'a> {
+    lat: &'a str,
+    lon: &'a str,
+    #[serde(rename = "appid")]
+    app_id: &'a str,
+    exclude: &'static str,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OneCallCurrent {
+    uvi: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OpenWeatherMapOneCallResponse {
+    current: Option<OneCallCurrent>,
+    #[serde(default)]
+    alerts: Vec<WeatherAlert>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OpenWeatherMapAirPollutionQuery<'a> {
+    lat: &'a str,
+    lon: &'a str,
+    #[serde(rename = "appid")]
+    app_id: &'a str,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OWMAirPollutionIndex {
+    aqi: u8,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OWMAirPollutionEntry {
+    main: OWMAirPollutionIndex,
+    components: Pollutants,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OpenWeatherMapAirPollutionResponse {
+    list: Vec<OWMAirPollutionEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OWMMain {
+    pub temp: f32,
+    pub feels_like: f32,
+    pub temp_min: f32,
+    pub temp_max: f32,
+    /// Atmospheric pressure in hPa.
+    pub pressure: f32,
+    /// Relative humidity as a percentage.
+    pub humidity: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OWMSys {
+    pub country: String,
+    pub sunrise: i64,
+    pub sunset: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OWMWind {
+    /// Wind speed in metres per second.
+    pub speed: f32,
+    /// Wind direction in meteorological degrees.
+    #[serde(default)]
+    pub deg: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OWMClouds {
+    /// Cloud cover as a percentage.
+    pub all: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OWMWeather {
+    pub main: String,
+    pub description: String,
+    // TODO
+    pub icon: String,
+}
+
+fn emoji_for_icon(icon: &str) -> &'static str {
+    match icon {
+        "01d" => ":sunny:",
+        "01n" => ":crescent_moon:",
+        "02d" => ":white_sun_cloud:",
+        "02n" | "03d" | "03n" | "04d" | "04n" => ":cloud:",
+        "09d" | "09n" | "10d" | "10n" => ":cloud_rain:",
+        "11d" | "11n" => ":cloud_lightning:",
+        "13d" | "13n" => ":cloud_snow:",
+        "50d" | "50n" => ":fog:",
+        _ => ":thermometer:",
+    }
+}
+
+/// Returns the URL of OpenWeatherMap's image for the given icon code.
+fn icon_url(icon: &str) -> String {
+    format!("https://openweathermap.org/img/wn/{icon}@2x.png")
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OpenWeatherMapResponse {
+    pub dt: i64,
+    pub main: OWMMain,
+    pub sys: OWMSys,
+    pub weather: Vec<OWMWeather>,
+    pub wind: OWMWind,
+    pub clouds: OWMClouds,
+    /// Visibility in metres.
+    pub visibility: Option<u32>,
+    /// Offset from UTC in seconds.
+    pub timezone: i32,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OWMCity {
+    pub name: String,
+    pub country: String,
+    /// Offset from UTC in seconds.
+    pub timezone: i32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OWMForecastEntry {
+    pub dt: i64,
+    pub main: OWMMain,
+    pub weather: Vec<OWMWeather>,
+    /// Probability of precipitation, between 0 and 1.
+    #[serde(default)]
+    pub pop: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OpenWeatherMapForecastResponse {
+    pub list: Vec<OWMForecastEntry>,
+    pub city: OWMCity,
+}
+
+impl OpenWeatherMapClient {
+    pub fn new<T: Into<String>>(api_key: T, one_call: bool) -> Self {
+        Self {
+            client: make_client(),
+            api_key: api_key.into(),
+            one_call,
+        }
+    }
+
+    /// Whether the API key is subscribed to One Call 3.0, which alerts and the UV index need.
+    pub fn has_one_call(&self) -> bool {
+        self.one_call
+    }
+
+    /// Returns the current weather at the given coordinates.
+    pub async fn get(&self, lat: &str, lon: &str) -> Result<OpenWeatherMapResponse, WeatherError> {
+        let query = OpenWeatherMapQuery {
+            lat,
+            lon,
+            app_id: &self.api_key,
+            units: "metric",
+        };
+
+        let response = self
+            .client
+            .get("https://api.openweathermap.org/data/2.5/weather")
+            .query(&query)
+            .send()
+            .await?;
+
+        Ok(check_response(response).await?.json().await?)
+    }
+
+    /// Returns the 5 day / 3 hour forecast for the given coordinates.
+    pub async fn forecast(
+        &self,
+        lat: &str,
+        lon: &str,
+    ) -> Result<OpenWeatherMapForecastResponse, WeatherError> {
+        let query = OpenWeatherMapQuery {
+            lat,
+            lon,
+            app_id: &self.api_key,
+            units: "metric",
+        };
+
+        let response = self
+            .client
+            .get("https://api.openweathermap.org/data/2.5/forecast")
+            .query(&query)
+            .send()
+            .await?;
+
+        Ok(check_response(response).await?.json().await?)
+    }
+
+    /// Returns any active severe weather alerts for the given coordinates.  This needs a One
+    /// Call 3.0 subscription.
+    pub async fn get_alerts(
+        &self,
+        lat: &str,
+        lon: &str,
+    ) -> Result<Vec<WeatherAlert>, WeatherError> {
+        let query = OpenWeatherMapOneCallQuery {
+            lat,
+            lon,
+            app_id: &self.api_key,
+            exclude: "current,minutely,hourly,daily",
+        };
+
+        let response = self
+            .client
+            .get("https://api.openweathermap.org/data/3.0/onecall")
+            .query(&query)
+            .send()
+            .await?;
+
+        let payload: OpenWeatherMapOneCallResponse = check_response(response).await?.json().await?;
+        Ok(payload.alerts)
+    }
+
+    /// Returns the current UV index at the given coordinates, or `None` if the API key
+    /// isn't subscribed to One Call 3.0.
+    pub async fn uv_index(&self, lat: &str, lon: &str) -> Result<Option<f32>, WeatherError> {
+        if !self.one_call {
+            return Ok(None);
+        }
+
+        let query = OpenWeatherMapOneCallQuery {
+            lat,
+            lon,
+            app_id: &self.api_key,
+            exclude: "minutely,hourly,daily,alerts",
+        };
+
+        let response = self
+            .client
+            .get("https://api.openweathermap.org/data/3.0/onecall")
+            .query(&query)
+            .send()
+            .await?;
+
+        let payload: OpenWeatherMapOneCallResponse = check_response(response).await?.json().await?;
+        payload
+            .current
+            .map(|current| Some(current.uvi))
+            .ok_or_else(|| WeatherError::Upstream(String::from("missing current conditions")))
+    }
+
+    /// Returns the current air quality at the given coordinates.
+    pub async fn air_pollution(&self, lat: &str, lon: &str) -> Result<AirQuality, WeatherError> {
+        let query = OpenWeatherMapAirPollutionQuery {
+            lat,
+            lon,
+            app_id: &self.api_key,
+        };
+
+        let response = self
+            .client
+            .get("https://api.openweathermap.org/data/2.5/air_pollution")
+            .query(&query)
+            .send()
+            .await?;
+
+        let payload: OpenWeatherMapAirPollutionResponse =
+            check_response(response).await?.json().await?;
+        let entry = payload
+            .list
+            .into_iter()
+            .next()
+            .ok_or(WeatherError::NotFound)?;
+        let level = AqiLevel::from_index(entry.main.aqi).ok_or_else(|| {
+            WeatherError::Upstream(format!("unknown air quality index {}", entry.main.aqi))
+        })?;
+
+        Ok(AirQuality {
+            level,
+            pollutants: entry.components,
+        })
+    }
+}
+
+impl From<OpenWeatherMapResponse> for CurrentWeather {
+    fn from(response: OpenWeatherMapResponse) -> Self {
+        let weather = response.weather.first();
+
+        Self {
+            description: weather
+                .map(|weather| weather.description.clone())
+                .unwrap_or_default(),
+            emoji: weather
+                .map(|weather| emoji_for_icon(&weather.icon))
+                .unwrap_or_default(),
+            icon_url: weather.map(|weather| icon_url(&weather.icon)),
+            temp: response.main.temp,
+            feels_like: response.main.feels_like,
+            humidity: response.main.humidity,
+            pressure: response.main.pressure,
+            wind_speed: response.wind.speed,
+            wind_deg: response.wind.deg,
+            cloud_cover: response.clouds.all,
+            visibility: response.visibility.map(|visibility| visibility as f32),
+            uv_index: None,
+            sunrise: Some(response.sys.sunrise),
+            sunset: Some(response.sys.sunset),
+            utc_offset: response.timezone,
+        }
+    }
+}
+
+impl From<OpenWeatherMapForecastResponse> for Forecast {
+    fn from(response: OpenWeatherMapForecastResponse) -> Self {
+        let entries = response
+            .list
+            .into_iter()
+            .map(|entry| {
+                let icon = entry.weather.first().map(|weather| weather.icon.as_str());
+
+                ForecastEntry {
+                    dt: entry.dt,
+                    temp: entry.main.temp,
+                    temp_min: entry.main.temp_min,
+                    temp_max: entry.main.temp_max,
+                    emoji: icon.map(emoji_for_icon).unwrap_or_default(),
+                    is_day: icon.is_none_or(|icon| icon.ends_with('d')),
+                    pop: entry.pop,
+                }
+            })
+            .collect();
+
+        Self {
+            utc_offset: response.city.timezone,
+            entries,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenWeatherMapClient {
+    async fn current(&self, lat: &str, lon: &str) -> Result<CurrentWeather, WeatherError> {
+        let mut current = CurrentWeather::from(self.get(lat, lon).await?);
+        // The UV index is a nice to have, so failing to get it shouldn't fail the lookup.
+        current.uv_index = self.uv_index(lat, lon).await.unwrap_or_else(|why| {
+            warn!("failed to get UV index: {why:?}");
+            None
+        });
+        Ok(current)
+    }
+
+    async fn forecast(&self, lat: &str, lon: &str) -> Result<Forecast, WeatherError> {
+        OpenWeatherMapClient::forecast(self, lat, lon)
+            .await
+            .map(Forecast::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_current_weather() {
+        let response: OpenWeatherMapResponse =
+            serde_json::from_str(include_str!("fixtures/owm_current.json")).unwrap();
+        assert_eq!(response.name, "London");
+        assert_eq!(response.sys.country, "GB");
+
+        let weather = CurrentWeather::from(response);
+        assert_eq!(weather.description, "broken clouds");
+        assert_eq!(weather.emoji, ":cloud:");
+        assert_eq!(
+            weather.icon_url.as_deref(),
+            Some("https://openweathermap.org/img/wn/04d@2x.png")
+        );
+        assert_eq!(weather.temp, 14.62);
+        assert_eq!(weather.humidity, 77.);
+        assert_eq!(weather.wind_deg, 240.);
+        assert_eq!(weather.visibility, Some(10000.));
+        assert_eq!(weather.sunrise, Some(1697437436));
+        assert_eq!(weather.utc_offset, 3600);
+    }
+
+    #[test]
+    fn parses_forecast() {
+        let response: OpenWeatherMapForecastResponse =
+            serde_json::from_str(include_str!("fixtures/owm_forecast.json")).unwrap();
+        let forecast = Forecast::from(response);
+
+        assert_eq!(forecast.utc_offset, 3600);
+        assert_eq!(forecast.entries.len(), 3);
+        assert_eq!(forecast.entries[0].emoji, ":cloud_rain:");
+        assert!(forecast.entries[0].is_day);
+        assert_eq!(forecast.entries[0].pop, 0.42);
+        assert!(!forecast.entries[1].is_day);
+        // The last entry has no chance of rain, so it leaves out `pop`.
+        assert_eq!(forecast.entries[2].pop, 0.);
+    }
+
+    #[test]
+    fn parses_air_pollution() {
+        let response: OpenWeatherMapAirPollutionResponse =
+            serde_json::from_str(include_str!("fixtures/owm_air_pollution.json")).unwrap();
+        let entry = &response.list[0];
+
+        assert_eq!(AqiLevel::from_index(entry.main.aqi), Some(AqiLevel::Fair));
+        assert_eq!(entry.components.pm2_5, 6.45);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::client::make_client;
+use anyhow::{Context, Result};
+use reqwest::{Client as ReqClient, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::models::zoo::pool::ImagePool;
+
+mod pool;
+mod posts;
+
+pub use posts::{AnimalPost, AnimalPostStore, RankedImage, FAVOURITE_EMOJI, UPVOTE_EMOJI};
+
+/// How long the list of dog breeds is cached for.
+const BREED_LIST_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How many unserved images each pool is topped up to.
+const POOL_SIZE: i64 = 10;
+
+/// Every dog.ceo breed, along with its sub-breeds.
+pub type BreedList = BTreeMap<String, Vec<String>>;
+
+#[derive(Debug, Deserialize)]
+struct Dog {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Breeds {
+    message: BreedList,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cat {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Fox {
+    image: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Duck {
+    url: String,
+}
+
+/// A dog.ceo breed, optionally narrowed down to one of its sub-breeds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breed {
+    pub breed: String,
+    pub sub_breed: Option<String>,
+}
+
+impl Breed {
+    /// Finds the breed described by the given words, which may be a breed (`shiba`), a
+    /// breed and sub-breed in either order (`hound afghan`, `afghan hound`) or just a
+    /// sub-breed (`golden`).
+    pub fn resolve(breeds: &BreedList, words: &[&str]) -> Option<Self> {
+        let words: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+        let has_sub_breed = |breed: &str, sub_breed: &str| {
+            breeds
+                .get(breed)
+                .is_some_and(|sub_breeds| sub_breeds.iter().any(|s| s == sub_breed))
+        };
+
+        match words.as_slice() {
+            [breed] if breeds.contains_key(breed) => Some(Self {
+                breed: breed.clone(),
+                sub_breed: None,
+            }),
+            [sub_breed] => breeds
+                .iter()
+                .find(|(_, sub_breeds)| sub_breeds.contains(sub_breed))
+                .map(|(breed, _)| Self {
+                    breed: breed.clone(),
+                    sub_breed: Some(sub_breed.clone()),
+                }),
+            [breed, sub_breed] | [sub_breed, breed] if has_sub_breed(breed, sub_breed) => {
+                Some(Self {
+                    breed: breed.clone(),
+                    sub_breed: Some(sub_breed.clone()),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Lists the dog breeds and sub-breeds which have image pools.
+fn breeds_from_pool_keys(keys: &[String]) -> BreedList {
+    let mut breeds = BreedList::new();
+    for key in keys {
+        if let Some(Species::Dog(Some(breed))) = Species::from_pool_key(key) {
+            let sub_breeds = breeds.entry(breed.breed).or_default();
+            sub_breeds.extend(breed.sub_breed);
+        }
+    }
+    breeds
+}
+
+/// Something we can show a picture of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Species {
+    Dog(Option<Breed>),
+    Cat,
+    Fox,
+    Duck,
+}
+
+impl Species {
+    /// Identifies the image pool for this species, e.g. `dog` or `dog/hound/afghan`.
+    pub fn pool_key(&self) -> String {
+        match self {
+            Species::Dog(None) => String::from("dog"),
+            Species::Dog(Some(Breed { breed, sub_breed })) => match sub_breed {
+                Some(sub_breed) => format!("dog/{breed}/{sub_breed}"),
+                None => format!("dog/{breed}"),
+            },
+            Species::Cat => String::from("cat"),
+            Species::Fox => String::from("fox"),
+            Species::Duck => String::from("duck"),
+        }
+    }
+
+    pub fn from_pool_key(key: &str) -> Option<Self> {
+        let breed = |breed: &str, sub_breed: Option<&str>| Breed {
+            breed: breed.to_string(),
+            sub_breed: sub_breed.map(str::to_string),
+        };
+
+        match key.split('/').collect::<Vec<_>>().as_slice() {
+            ["dog"] => Some(Species::Dog(None)),
+            ["dog", b] => Some(Species::Dog(Some(breed(b, None)))),
+            ["dog", b, sub_breed] => Some(Species::Dog(Some(breed(b, Some(sub_breed))))),
+            ["cat"] => Some(Species::Cat),
+            ["fox"] => Some(Species::Fox),
+            ["duck"] => Some(Species::Duck),
+            _ => None,
+        }
+    }
+
+    /// The upstream service images of this species come from.
+    pub fn source(&self) -> &'static str {
+        match self {
+            Species::Dog(_) => "dog.ceo",
+            Species::Cat => "cataas",
+            Species::Fox => "randomfox.ca",
+            Species::Duck => "random-d.uk",
+        }
+    }
+}
+
+pub struct AnimalGateway {
+    client: ReqClient,
+    breeds: Mutex<Option<(Instant, Arc<BreedList>)>>,
+    images: ImagePool<'static>,
+}
+
+impl AnimalGateway {
+    pub fn new(pool: &'static Pool<Sqlite>) -> Self {
+        Self {
+            client: make_client(),
+            breeds: Mutex::new(None),
+            images: ImagePool::new(pool),
+        }
+    }
+
+    async fn _get<T: DeserializeOwned>(&self, source: &'static str, url: &str) -> Result<T> {
+        let response = async {
+            self.client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        };
+
+        response
+            .await
+            .with_context(|| format!("failed to get {url} from {source}"))
+    }
+
+    /// Returns every dog breed, fetching the list from dog.ceo if the cached copy is stale.
+    ///
+    /// If dog.ceo is unavailable, the stale copy is used, or failing that the breeds which
+    /// have pooled images, so that pooled breeds can still be shown.
+    pub async fn get_dog_breeds(&self) -> Result<Arc<BreedList>> {
+        let mut cached = self.breeds.lock().await;
+        if let Some((fetched_at, breeds)) = cached.as_ref() {
+            if fetched_at.elapsed() < BREED_LIST_TTL {
+                return Ok(breeds.clone());
+            }
+        }
+
+        let why = match self
+            ._get::<Breeds>("dog.ceo", "https://dog.ceo/api/breeds/list/all")
+            .await
+        {
+            Ok(breeds) => {
+                let breeds = Arc::new(breeds.message);
+                *cached = Some((Instant::now(), breeds.clone()));
+                return Ok(breeds);
+            }
+            Err(why) => why,
+        };
+
+        if let Some((_, breeds)) = cached.as_ref() {
+            warn!("using a stale list of dog breeds: {why:?}");
+            return Ok(breeds.clone());
+        }
+
+        let pooled = breeds_from_pool_keys(&self.images.keys().await?);
+        if pooled.is_empty() {
+            return Err(why);
+        }
+        warn!("using the dog breeds in the image pool: {why:?}");
+        Ok(Arc::new(pooled))
+    }
+
+    /// Returns the URL of an image of the given species, preferring a prefetched image.
+    ///
+    /// If the pool is empty and the upstream source is unavailable, a previously served
+    /// image is returned instead.
+    pub async fn get_image(&self, species: &Species) -> Result<String> {
+        let key = species.pool_key();
+        match self.images.take(&key).await {
+            Ok(Some(url)) => return Ok(url),
+            Ok(None) => {}
+            Err(why) => warn!("failed to read image pool: {why:?}"),
+        }
+
+        match self.fetch(species).await {
+            Ok(url) => {
+                // Record the image so that the pool gets refilled and has a fallback.
+                if let Err(why) = self.images.add(&key, &url, true).await {
+                    warn!("failed to write image pool: {why:?}");
+                }
+                Ok(url)
+            }
+            Err(why) => match self.images.fallback(&key).await {
+                Ok(Some(url)) => {
+                    warn!("falling back to a previously served image: {why:?}");
+                    Ok(url)
+                }
+                _ => Err(why),
+            },
+        }
+    }
+
+    /// Tops up every image pool which has been used, along with those for each species.
+    pub async fn refill(&self) -> Result<()> {
+        let mut keys = self.images.keys().await?;
+        for species in [
+            Species::Dog(None),
+            Species::Cat,
+            Species::Fox,
+            Species::Duck,
+        ] {
+            let key = species.pool_key();
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        // Don't keep hammering a source which is down.
+        let mut failed_sources = HashSet::new();
+
+        for key in keys {
+            let Some(species) = Species::from_pool_key(&key) else {
+                continue;
+            };
+            if failed_sources.contains(species.source()) {
+                continue;
+            }
+
+            self.images.trim(&key).await?;
+            for _ in self.images.available(&key).await?..POOL_SIZE {
+                match self.fetch(&species).await {
+                    Ok(url) => self.images.add(&key, &url, false).await?,
+                    Err(why) => {
+                        warn!("failed to refill {key} pool: {why:?}");
+                        failed_sources.insert(species.source());
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the URL of a new image of the given species from its upstream source.
+    async fn fetch(&self, species: &Species) -> Result<String> {
+        let source = species.source();
+
+        match species {
+            Species::Dog(None) => self
+                ._get::<Dog>(source, "https://dog.ceo/api/breeds/image/random")
+                .await
+                .map(|dog| dog.message),
+            Species::Dog(Some(breed)) => {
+                let url = match &breed.sub_breed {
+                    Some(sub_breed) => format!(
+                        "https://dog.ceo/api/breed/{}/{sub_breed}/images/random",
+                        breed.breed
+                    ),
+                    None => format!("https://dog.ceo/api/breed/{}/images/random", breed.breed),
+                };
+
+                self._get::<Dog>(source, &url).await.map(|dog| dog.message)
+            }
+            Species::Cat => self
+                .get_cat(None, None)
+                .await?
+                .with_context(|| format!("{source} has no cats")),
+            Species::Fox => self
+                ._get::<Fox>(source, "https://randomfox.ca/floof/")
+                .await
+                .map(|fox| fox.image),
+            Species::Duck => self
+                ._get::<Duck>(source, "https://random-d.uk/api/v2/random")
+                .await
+                .map(|duck| duck.url),
+        }
+    }
+
+    /// Returns the URL of an image of a cat, optionally with the given tag and caption.
+    /// Returns `None` if there are no cats with that tag.
+    pub async fn get_cat(&self, tag: Option<&str>, says: Option<&str>) -> Result<Option<String>> {
+        let mut url = Url::parse("https://cataas.com/cat")?;
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .map_err(|_| anyhow::anyhow!("cataas url cannot be a base"))?;
+            segments.extend(tag);
+            if let Some(says) = says {
+                segments.push("says").push(says);
+            }
+        }
+        url.query_pairs_mut().append_pair("json", "true");
+
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| "failed to get a cat from cataas")?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let cat: Cat = async { response.error_for_status()?.json().await }
+            .await
+            .with_context(|| "failed to get a cat from cataas")?;
+        Ok(Some(format!("https://cataas.com{}", cat.url)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breeds() -> BreedList {
+        [
+            ("husky", vec![]),
+            ("shepherd", vec!["australian", "german"]),
+            ("hound", vec!["afghan", "basset"]),
+        ]
+        .into_iter()
+        .map(|(breed, sub_breeds)| {
+            (
+                breed.to_string(),
+                sub_breeds.into_iter().map(String::from).collect(),
+            )
+        })
+        .collect()
+    }
+
+    fn resolve(words: &str) -> Option<Breed> {
+        Breed::resolve(&breeds(), &words.split_whitespace().collect::<Vec<_>>())
+    }
+
+    fn breed(breed: &str, sub_breed: Option<&str>) -> Option<Breed> {
+        Some(Breed {
+            breed: breed.to_string(),
+            sub_breed: sub_breed.map(String::from),
+        })
+    }
+
+    #[test]
+    fn resolves_breeds() {
+        assert_eq!(resolve("husky"), breed("husky", None));
+        assert_eq!(resolve("Husky"), breed("husky", None));
+        assert_eq!(resolve("shepherd"), breed("shepherd", None));
+        assert_eq!(
+            resolve("german shepherd"),
+            breed("shepherd", Some("german"))
+        );
+        assert_eq!(
+            resolve("shepherd german"),
+            breed("shepherd", Some("german"))
+        );
+        assert_eq!(resolve("afghan"), breed("hound", Some("afghan")));
+    }
+
+    #[test]
+    fn rejects_unknown_breeds() {
+        assert_eq!(resolve("dragon"), None);
+        assert_eq!(resolve("german hound"), None);
+        assert_eq!(resolve("husky german"), None);
+        assert_eq!(resolve("big german shepherd"), None);
+    }
+
+    #[test]
+    fn lists_pooled_breeds() {
+        let keys = [
+            "dog",
+            "dog/hound/afghan",
+            "cat",
+            "dog/shiba",
+            "dog/hound/basset",
+        ]
+        .map(String::from);
+        let breeds = breeds_from_pool_keys(&keys);
+
+        assert_eq!(breeds.len(), 2);
+        assert_eq!(breeds["hound"], ["afghan", "basset"]);
+        assert!(breeds["shiba"].is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+
+/// How many served images are kept per pool to fall back on when the upstream is down.
+const FALLBACK_SIZE: i64 = 50;
+
+/// Prefetched image URLs, grouped into pools by species (and breed).
+///
+/// Each URL is served once while it is fresh, after which it is kept around as a fallback
+/// for when the upstream source is unavailable.
+pub(super) struct ImagePool<'pool> {
+    pool: &'pool Pool<Sqlite>,
+}
+
+impl<'pool> ImagePool<'pool> {
+    pub fn new(pool: &'pool Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    /// Adds a URL to the given pool.  URLs which are being served immediately should be
+    /// added as already served.
+    pub async fn add(&self, key: &str, url: &str, served: bool) -> Result<()> {
+        let fetched_at = Utc::now().timestamp();
+
+        sqlx::query!(
+            "
+        INSERT INTO animal_images (pool, url, fetched_at, served)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (pool, url) DO UPDATE SET served = served OR excluded.served
+            ",
+            key,
+            url,
+            fetched_at,
+            served
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to add image to {key} pool"))
+    }
+
+    /// Takes the oldest unserved URL from the given pool, marking it as served.
+    pub async fn take(&self, key: &str) -> Result<Option<String>> {
+        sqlx::query_scalar!(
+            "
+        UPDATE animal_images SET served = TRUE
+        WHERE id = (
+            SELECT id FROM animal_images
+            WHERE pool = ? AND NOT served
+            ORDER BY fetched_at
+            LIMIT 1
+        )
+        RETURNING url
+            ",
+            key
+        )
+        .fetch_optional(self.pool)
+        .await
+        .with_context(|| format!("failed to take image from {key} pool"))
+    }
+
+    /// Returns a random previously served URL from the given pool.
+    pub async fn fallback(&self, key: &str) -> Result<Option<String>> {
+        sqlx::query_scalar!(
+            "SELECT url FROM animal_images WHERE pool = ? AND served ORDER BY RANDOM() LIMIT 1",
+            key
+        )
+        .fetch_optional(self.pool)
+        .await
+        .with_context(|| format!("failed to get fallback image from {key} pool"))
+    }
+
+    /// Returns the number of unserved URLs in the given pool.
+    pub async fn available(&self, key: &str) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!: i64" FROM animal_images WHERE pool = ? AND NOT served"#,
+            key
+        )
+        .fetch_one(self.pool)
+        .await
+        .with_context(|| format!("failed to count images in {key} pool"))
+    }
+
+    /// Returns the keys of every pool which has ever been used.
+    pub async fn keys(&self) -> Result<Vec<String>> {
+        sqlx::query_scalar!("SELECT DISTINCT pool FROM animal_images")
+            .fetch_all(self.pool)
+            .await
+            .with_context(|| "failed to get image pools")
+    }
+
+    /// Forgets all but the most recent served URLs in the given pool.
+    pub async fn trim(&self, key: &str) -> Result<()> {
+        sqlx::query!(
+            "
+        DELETE FROM animal_images
+        WHERE pool = ? AND served AND id NOT IN (
+            SELECT id FROM animal_images
+            WHERE pool = ? AND served
+            ORDER BY fetched_at DESC
+            LIMIT ?
+        )
+            ",
+            key,
+            key,
+            FALLBACK_SIZE
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to trim {key} pool"))
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+
+/// Reacting to an animal picture with this saves it to your favourites.
+pub const FAVOURITE_EMOJI: &str = "⭐";
+/// Reacting to an animal picture with this upvotes it.
+pub const UPVOTE_EMOJI: &str = "👍";
+
+#[derive(Debug, Clone)]
+pub struct AnimalPost {
+    pub species: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RankedImage {
+    pub url: String,
+    pub votes: i64,
+}
+
+/// Keeps track of the animal pictures we've posted and how people reacted to them.
+pub struct AnimalPostStore<'pool> {
+    pool: &'pool Pool<Sqlite>,
+}
+
+impl<'pool> AnimalPostStore<'pool> {
+    pub fn new(pool: &'pool Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record_post(
+        &self,
+        message_id: i64,
+        guild: Option<i64>,
+        channel: i64,
+        species: &str,
+        url: &str,
+    ) -> Result<()> {
+        let posted_at = Utc::now().timestamp();
+
+        sqlx::query!(
+            "
+        INSERT OR IGNORE INTO animal_posts (message_id, guild, channel, species, url, posted_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+            ",
+            message_id,
+            guild,
+            channel,
+            species,
+            url,
+            posted_at
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to record animal post {message_id}"))
+    }
+
+    /// Records a reaction to a message, if it is one of our animal posts.
+    pub async fn add_reaction(&self, message_id: i64, user_id: i64, emoji: &str) -> Result<()> {
+        sqlx::query!(
+            "
+        INSERT OR IGNORE INTO animal_reactions (message_id, user_id, emoji)
+        SELECT ?, ?, ?
+        WHERE EXISTS (SELECT 1 FROM animal_posts WHERE message_id = ?)
+            ",
+            message_id,
+            user_id,
+            emoji,
+            message_id
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to add reaction to animal post {message_id}"))
+    }
+
+    pub async fn remove_reaction(&self, message_id: i64, user_id: i64, emoji: &str) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM animal_reactions WHERE message_id = ? AND user_id = ? AND emoji = ?",
+            message_id,
+            user_id,
+            emoji
+        )
+        .execute(self.pool)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("failed to remove reaction from animal post {message_id}"))
+    }
+
+    /// Returns the pictures the given user has favourited, most recent first.  Only
+    /// pictures whose species key starts with `species` are included.
+    pub async fn favourites(
+        &self,
+        user_id: i64,
+        species: &str,
+        limit: i64,
+    ) -> Result<Vec<AnimalPost>> {
+        let species_pattern = format!("{species}%");
+
+        sqlx::query_as!(
+            AnimalPost,
+            r#"
+        SELECT p.species AS "species!", p.url AS "url!"
+        FROM animal_posts p
+        JOIN animal_reactions r ON r.message_id = p.message_id
+        WHERE r.user_id = ? AND r.emoji = ? AND p.species LIKE ?
+        GROUP BY p.url
+        ORDER BY MAX(p.posted_at) DESC
+        LIMIT ?
+            "#,
+            user_id,
+            FAVOURITE_EMOJI,
+            species_pattern,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| format!("failed to get favourites for user {user_id}"))
+    }
+
+    /// Returns the most upvoted pictures posted in the given guild.
+    pub async fn top(&self, guild: i64, limit: i64) -> Result<Vec<RankedImage>> {
+        sqlx::query_as!(
+            RankedImage,
+            r#"
+        SELECT p.url, COUNT(*) AS "votes!: i64"
+        FROM animal_posts p
+        JOIN animal_reactions r ON r.message_id = p.message_id
+        WHERE p.guild = ? AND r.emoji = ?
+        GROUP BY p.url
+        ORDER BY 2 DESC
+        LIMIT ?
+            "#,
+            guild,
+            UPVOTE_EMOJI,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await
+        .with_context(|| format!("failed to get top animal pictures for guild {guild}"))
+    }
+}